@@ -0,0 +1,523 @@
+//! Command-line interface to manage the music library without a display.
+
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json;
+use std::{fs::File, io::{self, Write}, panic, path::{Path, PathBuf}, thread};
+use music_player_rs::music_manager::{
+    Error,
+    Result,
+    config::Config,
+    miner::{Miner, MinerEvent},
+    search_manager::SearchManager,
+    model::{Song, SmartPlaylist, PlaylistOrder, Period, format_date, format_duration,
+        format_long_duration, format_size, now},
+};
+
+/// Length of the longest bar of a histogram, in characters.
+const HISTOGRAM_WIDTH: i64 = 40;
+
+/// Returns the subcommands of the command-line interface.
+pub fn subcommands<'a, 'b>() -> Vec<App<'a, 'b>> {
+    vec![
+        SubCommand::with_name("mine")
+            .about("Mines the music files of the library, or of the given directories")
+            .arg(Arg::with_name("directory")
+                .value_name("DIRECTORY")
+                .help("Directories to mine instead of the library roots")
+                .multiple(true)),
+        SubCommand::with_name("list")
+            .about("Lists the songs, albums or performers in the library")
+            .arg(Arg::with_name("what")
+                .possible_values(&["songs", "albums", "performers"])
+                .default_value("songs"))
+            .arg(json_arg()),
+        SubCommand::with_name("search")
+            .about("Searches songs, e.g. \"P: performer G: rock\"")
+            .arg(Arg::with_name("query")
+                .value_name("QUERY")
+                .help("Query using the T:, P:, A: and G: text prefixes, the Y:, R: and PC: \
+                    number prefixes with =, <, >, <= or >=, e.g. \"Y:<1980\", and \"never played\"")
+                .required(true)
+                .multiple(true))
+            .arg(json_arg()),
+        SubCommand::with_name("stats")
+            .about("Shows statistics of the library: counts, duration, size, formats, songs \
+                with missing tags or art, and songs per year")
+            .arg(json_arg()),
+        SubCommand::with_name("history")
+            .about("Shows the listening history, its most played performers or albums, or a \
+                timeline of the listening per day")
+            .arg(Arg::with_name("period")
+                .long("period")
+                .value_name("PERIOD")
+                .help("\"all\", \"today\", \"this-month\", \"this-year\", a year (2026) or a \
+                    month (2026-10)")
+                .default_value("all"))
+            .arg(Arg::with_name("top")
+                .long("top")
+                .value_name("WHAT")
+                .help("Shows the most played performers or albums")
+                .possible_values(&["performers", "albums"])
+                .conflicts_with("timeline"))
+            .arg(Arg::with_name("timeline")
+                .long("timeline")
+                .help("Shows the number of playbacks and the time listened per day"))
+            .arg(Arg::with_name("limit")
+                .long("limit")
+                .value_name("N")
+                .help("Maximum number of rows")
+                .takes_value(true))
+            .arg(json_arg()),
+        SubCommand::with_name("playlist")
+            .about("Lists the smart playlists, shows the songs of one, or saves or deletes it")
+            .arg(Arg::with_name("name")
+                .value_name("NAME")
+                .help("Name of the playlist"))
+            .arg(Arg::with_name("rules")
+                .long("rules")
+                .value_name("QUERY")
+                .help("Saves the playlist with the songs that match a query, e.g. \"G: rock \
+                    Y:<1980 R:>=4\"")
+                .requires("name")
+                .conflicts_with("delete"))
+            .arg(Arg::with_name("order")
+                .long("order")
+                .value_name("ORDER")
+                .help("Order of the saved playlist: \"title\" (default), \"random\", \
+                    \"most-played\", \"least-played\", \"top-rated\", \"recently-added\" or \
+                    \"recently-played\"")
+                .requires("rules"))
+            .arg(Arg::with_name("limit")
+                .long("limit")
+                .value_name("N")
+                .help("Maximum number of songs of the saved playlist")
+                .takes_value(true)
+                .requires("rules"))
+            .arg(Arg::with_name("delete")
+                .long("delete")
+                .help("Deletes the playlist")
+                .requires("name"))
+            .arg(json_arg()),
+        SubCommand::with_name("export")
+            .about("Exports the songs of the library as JSON")
+            .arg(Arg::with_name("file")
+                .value_name("FILE")
+                .help("Output file (standard output by default)")),
+    ]
+}
+
+/// Returns the arguments that override the configuration file.
+pub fn config_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .value_name("FILE")
+            .help("Configuration file")
+            .takes_value(true)
+            .global(true),
+        Arg::with_name("database")
+            .long("database")
+            .value_name("FILE")
+            .help("Database file")
+            .takes_value(true)
+            .global(true),
+        Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .value_name("FILE")
+            .help("Log file")
+            .takes_value(true)
+            .global(true),
+        Arg::with_name("v")
+            .short("v")
+            .multiple(true)
+            .help("Verbosity level")
+            .global(true),
+        Arg::with_name("library")
+            .short("l")
+            .long("library")
+            .value_name("DIRECTORY")
+            .help("Library root, replaces the configured roots (may be repeated)")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true),
+        Arg::with_name("exclude")
+            .long("exclude")
+            .value_name("GLOB")
+            .help("Pattern of paths not to mine, e.g. \"*/Podcasts/*\" (may be repeated)")
+            .multiple(true)
+            .number_of_values(1)
+            .global(true),
+        Arg::with_name("follow symlinks")
+            .long("follow-symlinks")
+            .help("Follows symbolic links while mining")
+            .global(true),
+    ]
+}
+
+/// Returns the configuration: the configuration file, overridden by the environment variables,
+/// overridden by the command-line flags.
+/// A configuration file given in the command line is used for the whole run, so the changes
+/// made in the GUI are saved to it.
+pub fn config(matches: &ArgMatches) -> Result<Config> {
+    let file = matches.value_of("config").map(Path::new);
+    Ok(override_config(Config::load(file)?, matches))
+}
+
+/// Overrides a configuration with the command-line flags.
+pub fn override_config(mut config: Config, matches: &ArgMatches) -> Config {
+    if let Some(database) = matches.value_of("database") {
+        config.database.path = PathBuf::from(database);
+    }
+    if let Some(file) = matches.value_of("output") {
+        config.log.file = PathBuf::from(file);
+    }
+    if matches.occurrences_of("v") > 0 {
+        config.log.verbosity = matches.occurrences_of("v");
+    }
+    if let Some(roots) = matches.values_of("library") {
+        config.library.roots = roots.map(PathBuf::from).collect();
+    }
+    if let Some(exclude) = matches.values_of("exclude") {
+        config.library.exclude.extend(exclude.map(str::to_string));
+    }
+    if matches.is_present("follow symlinks") {
+        config.library.follow_symlinks = true;
+    }
+    config
+}
+
+/// Returns the argument to print the output as JSON instead of a table.
+fn json_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("json")
+        .long("json")
+        .help("Prints the output as JSON")
+}
+
+/// Runs a subcommand with the given configuration, returning the exit code of the program.
+pub fn run(name: &str, matches: &ArgMatches, config: Config) -> i32 {
+    let result = match name {
+        "mine" => mine(matches, config),
+        "list" => list(matches, &config),
+        "search" => search(matches, &config),
+        "stats" => stats(matches, &config),
+        "history" => history(matches, &config),
+        "playlist" => playlist(matches, &config),
+        "export" => export(matches, &config),
+        _ => Ok(()),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        },
+    }
+}
+
+/// Mines the library, printing the progress to the standard error. A mining that fails is an
+/// error, so the command exits with an error code.
+fn mine(matches: &ArgMatches, mut config: Config) -> Result<()> {
+    if let Some(directories) = matches.values_of("directory") {
+        config.library.roots = directories.map(PathBuf::from).collect();
+    }
+    let mut miner = Miner::with_config(&config)?;
+    let listener = miner.get_listener();
+    let mining = thread::spawn(move || miner.mine());
+    let mut failure = None;
+    while let Ok(event) = listener.recv() {
+        match event {
+            MinerEvent::Ready => eprint!("Searching songs..."),
+            MinerEvent::Progress(progress) => eprint!("\r\x1b[KMining: {}", progress),
+            MinerEvent::FileFailed(path, error) | MinerEvent::DirectorySkipped(path, error) => {
+                eprintln!("\r\x1b[KSkipping {}: {}", path.to_string_lossy(), error);
+            },
+            MinerEvent::Finished(summary) => eprintln!("\r\x1b[KMining finished, {}", summary),
+            MinerEvent::Cancelled(summary) => eprintln!("\r\x1b[KMining cancelled, {}", summary),
+            MinerEvent::Failed(error) => {
+                eprintln!("\r\x1b[KMining failed");
+                failure = Some(error);
+            },
+            _ => {},
+        }
+    }
+    let result = match mining.join() {
+        Ok(result) => result,
+        Err(e) => panic::resume_unwind(e),
+    };
+    match (result, failure) {
+        (Ok(()), Some(error)) => Err(Error::Io(io::Error::new(io::ErrorKind::Other, error))),
+        (result, _) => result,
+    }
+}
+
+/// Lists the songs, albums or performers in the library.
+fn list(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let database = config.database()?;
+    let json = matches.is_present("json");
+    match matches.value_of("what").unwrap_or("songs") {
+        "albums" => {
+            let albums = database.albums()?;
+            if json {
+                return print_json(&albums);
+            }
+            let rows = albums.iter().map(|album| vec![
+                album.name.clone(),
+                optional(&album.year),
+                album.path.as_ref().map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ]).collect();
+            print_table(&["Album", "Year", "Path"], rows);
+        },
+        "performers" => {
+            let performers = database.performers()?;
+            if json {
+                return print_json(&performers);
+            }
+            let rows = performers.iter().map(|performer| vec![
+                performer.name.clone(),
+                format!("{:?}", performer.kind),
+            ]).collect();
+            print_table(&["Performer", "Type"], rows);
+        },
+        _ => print_songs(&database.songs()?, json)?,
+    }
+    Ok(())
+}
+
+/// Searches songs with the search manager's language.
+fn search(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let query: Vec<&str> = matches.values_of("query").map(|values| values.collect())
+        .unwrap_or_default();
+    let search_manager = SearchManager::with_database(config.database()?);
+    let songs = search_manager.songs(&query.join(" "))?;
+    print_songs(&songs, matches.is_present("json"))
+}
+
+/// Shows the statistics of the library: its number of songs, albums, performers and genres,
+/// its duration and size, the songs per format and per year, the songs with missing tags or
+/// without cover art, and the hidden and missing songs.
+fn stats(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let statistics = config.database()?.statistics()?;
+    if matches.is_present("json") {
+        return print_json(&statistics);
+    }
+    print_table(&["", "Count"], vec![
+        vec![String::from("Songs"), statistics.songs.to_string()],
+        vec![String::from("Albums"), statistics.albums.to_string()],
+        vec![String::from("Performers"), statistics.performers.to_string()],
+        vec![String::from("Genres"), statistics.genres.to_string()],
+        vec![String::from("Duration"), format_long_duration(statistics.duration)],
+        vec![String::from("Size"), format_size(statistics.size)],
+        vec![String::from("Missing tags"), statistics.missing_tags.to_string()],
+        vec![String::from("Missing art"), statistics.missing_art.to_string()],
+        vec![String::from("Hidden songs"), statistics.hidden.to_string()],
+        vec![String::from("Missing songs"), statistics.missing.to_string()],
+    ]);
+    println!();
+    print_table(&["Format", "Songs"], statistics.formats.iter()
+        .map(|&(ref format, songs)| vec![format.clone(), songs.to_string()])
+        .collect());
+    println!();
+    let most_songs = statistics.years.iter().map(|&(_, songs)| songs).max().unwrap_or(0);
+    print_table(&["Year", "Songs", ""], statistics.years.iter()
+        .map(|&(year, songs)| vec![year.to_string(), songs.to_string(),
+            "#".repeat(bar_length(songs, most_songs))])
+        .collect());
+    Ok(())
+}
+
+/// Returns the length of the bar of a histogram for a count, the longest bar being the one of
+/// the largest count.
+fn bar_length(count: i64, largest: i64) -> usize {
+    if largest <= 0 {
+        return 0;
+    }
+    ((count * HISTOGRAM_WIDTH + largest - 1) / largest) as usize
+}
+
+/// Shows the playbacks of a period, its most played performers or albums, or its listening per
+/// day.
+fn history(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let period = Period::parse(matches.value_of("period").unwrap_or("all"), now())?;
+    let limit = limit(matches)?;
+    let json = matches.is_present("json");
+    let database = config.database()?;
+    if matches.is_present("timeline") {
+        let timeline = database.listening_timeline(&period)?;
+        if json {
+            return print_json(&timeline);
+        }
+        let rows = timeline.iter().map(|day| vec![
+            day.date.clone(),
+            day.playbacks.to_string(),
+            format_duration(day.listened),
+        ]).collect();
+        print_table(&["Day", "Playbacks", "Listened"], rows);
+        return Ok(());
+    }
+    match matches.value_of("top") {
+        Some("performers") => {
+            let performers = database.top_performers(&period, limit)?;
+            if json {
+                return print_json(&performers);
+            }
+            let rows = performers.iter().map(|(performer, count)| vec![
+                performer.name.clone(),
+                count.to_string(),
+            ]).collect();
+            print_table(&["Performer", "Playbacks"], rows);
+        },
+        Some(_) => {
+            let albums = database.top_albums(&period, limit)?;
+            if json {
+                return print_json(&albums);
+            }
+            let rows = albums.iter().map(|(album, count)| vec![
+                album.name.clone(),
+                optional(&album.year),
+                count.to_string(),
+            ]).collect();
+            print_table(&["Album", "Year", "Playbacks"], rows);
+        },
+        None => {
+            let playbacks = database.history(&period, limit)?;
+            if json {
+                return print_json(&playbacks);
+            }
+            let rows = playbacks.iter().map(|playback| vec![
+                format_date(playback.started),
+                playback.song.title.clone(),
+                playback.song.performer.name.clone(),
+                format_duration(playback.listened),
+                String::from(if playback.completed { "yes" } else { "no" }),
+            ]).collect();
+            print_table(&["Day", "Title", "Performer", "Listened", "Completed"], rows);
+        },
+    }
+    Ok(())
+}
+
+/// Lists the smart playlists, or shows the songs of one of them, saves it or deletes it.
+fn playlist(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let json = matches.is_present("json");
+    let database = config.database()?;
+    let name = match matches.value_of("name") {
+        Some(name) => name,
+        None => {
+            let playlists = database.playlists()?;
+            if json {
+                return print_json(&playlists);
+            }
+            let rows = playlists.iter().map(|playlist| vec![
+                playlist.name.clone(),
+                playlist.rules.clone(),
+                playlist.order.name().to_owned(),
+                optional(&playlist.limit),
+            ]).collect();
+            print_table(&["Playlist", "Rules", "Order", "Limit"], rows);
+            return Ok(());
+        },
+    };
+    if let Some(rules) = matches.value_of("rules") {
+        let playlist = SmartPlaylist {
+            id: 0,
+            name: name.to_owned(),
+            rules: rules.to_owned(),
+            order: PlaylistOrder::from_name(matches.value_of("order").unwrap_or("title"))?,
+            limit: limit(matches)?,
+        };
+        SearchManager::with_database(database).save_playlist(&playlist)?;
+        eprintln!("Playlist {:?} saved", name);
+        return Ok(());
+    }
+    let playlist = database.playlist(name)?
+        .ok_or_else(|| Error::Parse(format!("Unknown playlist {:?}", name)))?;
+    if matches.is_present("delete") {
+        database.delete_playlist(playlist.id)?;
+        eprintln!("Playlist {:?} deleted", name);
+        return Ok(());
+    }
+    let songs = SearchManager::with_database(database).playlist_songs(&playlist)?;
+    print_songs(&songs, json)
+}
+
+/// Exports every song of the library, with its performer and album, as JSON.
+fn export(matches: &ArgMatches, config: &Config) -> Result<()> {
+    let database = config.database()?;
+    let songs = database.songs()?;
+    match matches.value_of("file") {
+        Some(file) => {
+            let file = File::create(file)?;
+            serde_json::to_writer_pretty(file, &songs).map_err(io::Error::from)?;
+        },
+        None => print_json(&songs)?,
+    }
+    Ok(())
+}
+
+/// Prints songs as a table or as JSON.
+fn print_songs(songs: &[Song], json: bool) -> Result<()> {
+    if json {
+        return print_json(&songs);
+    }
+    let rows = songs.iter().map(|song| vec![
+        song.title.clone(),
+        song.performer.name.clone(),
+        song.album.name.clone(),
+        song.genre.clone().unwrap_or_default(),
+        optional(&song.year),
+    ]).collect();
+    print_table(&["Title", "Performer", "Album", "Genre", "Year"], rows);
+    Ok(())
+}
+
+/// Prints a value as pretty JSON to the standard output.
+fn print_json<T: ::serde::Serialize>(value: &T) -> Result<()> {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    serde_json::to_writer_pretty(&mut handle, value).map_err(io::Error::from)?;
+    writeln!(handle)?;
+    Ok(())
+}
+
+/// Prints rows as a table, with a header and aligned columns.
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let header: Vec<String> = headers.iter().map(|header| header.to_string()).collect();
+    print_row(&header, &widths);
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    print_row(&separator, &widths);
+    for row in &rows {
+        print_row(row, &widths);
+    }
+}
+
+/// Prints a row of a table, padding every cell to the width of its column.
+fn print_row(row: &[String], widths: &[usize]) {
+    let cells: Vec<String> = row.iter().zip(widths)
+        .map(|(cell, width)| format!("{:width$}", cell, width = *width))
+        .collect();
+    println!("{}", cells.join("  ").trim_right());
+}
+
+/// Returns the value of the limit argument, if it is present.
+fn limit(matches: &ArgMatches) -> Result<Option<i64>> {
+    match matches.value_of("limit") {
+        Some(limit) => limit.parse::<i64>()
+            .map(Some)
+            .map_err(|_| Error::Parse(format!("Invalid limit {:?}", limit))),
+        None => Ok(None),
+    }
+}
+
+/// Returns an optional value as string, empty if there is none.
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map(ToString::to_string).unwrap_or_default()
+}
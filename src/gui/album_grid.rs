@@ -0,0 +1,256 @@
+use gdk_pixbuf::{InterpType, Pixbuf, PixbufLoader, PixbufLoaderExt};
+use glib::{self, StaticType};
+use gtk::{self, prelude::*};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, path::Path, rc::Rc, sync::mpsc,
+    thread};
+use music_player_rs::music_manager::{
+    cover,
+    model::{AlbumEntry, Song, format_duration},
+};
+
+/// Width and height, in pixels, of the album covers.
+const THUMBNAIL_SIZE: i32 = 128;
+
+/// A grid of the albums with their covers, next to the tracks of the activated album.
+/// The covers are loaded lazily, only for the albums that are scrolled into view: a thread reads
+/// the image data, and the main context decodes and scales it into a thumbnail. Thumbnails are
+/// kept while the grid exists, so refilling the grid does not read them again.
+pub struct AlbumGrid {
+    icon_view: gtk::IconView,
+    list_store: gtk::ListStore,
+    tracks_label: gtk::Label,
+    tracks_store: gtk::ListStore,
+    entries: RefCell<Vec<AlbumEntry>>,
+    rows: RefCell<HashMap<i64, gtk::TreeIter>>,
+    thumbnails: RefCell<HashMap<i64, Option<Pixbuf>>>,
+    requested: RefCell<HashSet<i64>>,
+    placeholder: Option<Pixbuf>,
+    requests: mpsc::Sender<AlbumEntry>,
+}
+
+impl AlbumGrid {
+
+    /// Creates the grid and the tracks' list, packing them into a container, and starts the
+    /// thread that reads the covers.
+    pub fn new(container: &gtk::Box) -> Rc<AlbumGrid> {
+        let list_store = gtk::ListStore::new(&[Pixbuf::static_type(), gtk::Type::String,
+            gtk::Type::I64]);
+        let icon_view = gtk::IconView::new_with_model(&list_store);
+        icon_view.set_pixbuf_column(0);
+        icon_view.set_markup_column(1);
+        icon_view.set_item_width(THUMBNAIL_SIZE);
+        icon_view.set_activate_on_single_click(true);
+        let grid_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        grid_window.set_shadow_type(gtk::ShadowType::In);
+        grid_window.add(&icon_view);
+
+        let tracks_store = gtk::ListStore::new(&[gtk::Type::String, gtk::Type::String,
+            gtk::Type::String]);
+        let tracks_view = gtk::TreeView::new_with_model(&tracks_store);
+        tracks_view.append_column(&tracks_column("#", 0, false));
+        tracks_view.append_column(&tracks_column("Title", 1, true));
+        tracks_view.append_column(&tracks_column("Length", 2, false));
+        let tracks_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        tracks_window.set_shadow_type(gtk::ShadowType::In);
+        tracks_window.add(&tracks_view);
+        let tracks_label = gtk::Label::new(None);
+        tracks_label.set_line_wrap(true);
+        let tracks_box = gtk::Box::new(gtk::Orientation::Vertical, 3);
+        tracks_box.pack_start(&tracks_label, false, true, 3);
+        tracks_box.pack_start(&tracks_window, true, true, 0);
+
+        let paned = gtk::Paned::new(gtk::Orientation::Horizontal);
+        paned.pack1(&grid_window, true, false);
+        paned.pack2(&tracks_box, false, true);
+        container.pack_start(&paned, true, true, 0);
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT_IDLE);
+        let grid = Rc::new(AlbumGrid {
+            icon_view: icon_view,
+            list_store: list_store,
+            tracks_label: tracks_label,
+            tracks_store: tracks_store,
+            entries: RefCell::new(Vec::new()),
+            rows: RefCell::new(HashMap::new()),
+            thumbnails: RefCell::new(HashMap::new()),
+            requested: RefCell::new(HashSet::new()),
+            placeholder: Pixbuf::new_from_file_at_size(Path::new("./src/ui/music_album.png"),
+                THUMBNAIL_SIZE, THUMBNAIL_SIZE).ok(),
+            requests: spawn_cover_reader(sender),
+        });
+
+        let grid_weak = Rc::downgrade(&grid);
+        receiver.attach(None, move |(id_album, data)| {
+            match grid_weak.upgrade() {
+                Some(grid) => {
+                    grid.set_thumbnail(id_album, data);
+                    glib::Continue(true)
+                },
+                None => glib::Continue(false),
+            }
+        });
+        if let Some(adjustment) = grid_window.get_vadjustment() {
+            let grid_weak = Rc::downgrade(&grid);
+            adjustment.connect_value_changed(move |_| {
+                if let Some(grid) = grid_weak.upgrade() {
+                    grid.load_visible();
+                }
+            });
+            let grid_weak = Rc::downgrade(&grid);
+            adjustment.connect_changed(move |_| {
+                if let Some(grid) = grid_weak.upgrade() {
+                    grid.load_visible();
+                }
+            });
+        }
+        let grid_weak = Rc::downgrade(&grid);
+        grid.icon_view.connect_map(move |_| {
+            if let Some(grid) = grid_weak.upgrade() {
+                grid.load_visible();
+            }
+        });
+        grid
+    }
+
+    /// Replaces the albums of the grid. The albums whose thumbnails were already loaded show
+    /// them; the rest show a placeholder until they are scrolled into view.
+    pub fn set_albums(grid: &Rc<AlbumGrid>, entries: Vec<AlbumEntry>) {
+        grid.list_store.clear();
+        let mut rows = grid.rows.borrow_mut();
+        rows.clear();
+        let thumbnails = grid.thumbnails.borrow();
+        for entry in &entries {
+            let thumbnail = match thumbnails.get(&entry.album.id) {
+                Some(&Some(ref thumbnail)) => Some(thumbnail.clone()),
+                _ => grid.placeholder.clone(),
+            };
+            let tree_iter = grid.list_store.insert_with_values(None, &[0, 1, 2],
+                &[&thumbnail as &ToValue, &album_markup(entry) as &ToValue,
+                    &entry.album.id as &ToValue]);
+            rows.insert(entry.album.id, tree_iter);
+        }
+        *grid.entries.borrow_mut() = entries;
+
+        let grid_weak = Rc::downgrade(grid);
+        gtk::idle_add(move || {
+            if let Some(grid) = grid_weak.upgrade() {
+                grid.load_visible();
+            }
+            glib::Continue(false)
+        });
+    }
+
+    /// Shows the tracks of an album, in the order they are given.
+    pub fn set_tracks(&self, album: &str, songs: &[Song]) {
+        self.tracks_label.set_text(album);
+        self.tracks_store.clear();
+        for song in songs {
+            let track = song.track.map(|track| track.to_string()).unwrap_or_default();
+            let length = song.duration.map(format_duration).unwrap_or_default();
+            self.tracks_store.insert_with_values(None, &[0, 1, 2],
+                &[&track as &ToValue, &song.title as &ToValue, &length as &ToValue]);
+        }
+    }
+
+    /// Calls a function with the album's id when the user activates an album.
+    pub fn connect_activated<F: Fn(i64) + 'static>(&self, function: F) {
+        self.icon_view.connect_item_activated(move |icon_view, tree_path| {
+            let model = match icon_view.get_model() {
+                Some(model) => model,
+                None => return,
+            };
+            if let Some(tree_iter) = model.get_iter(tree_path) {
+                if let Some(id_album) = model.get_value(&tree_iter, 2).get::<i64>() {
+                    function(id_album);
+                }
+            }
+        });
+    }
+
+    /// Requests the covers of the visible albums that were not requested yet.
+    fn load_visible(&self) {
+        let (start, end) = match self.icon_view.get_visible_range() {
+            Some(range) => range,
+            None => return,
+        };
+        let (start, end) = match (start.get_indices().first(), end.get_indices().first()) {
+            (Some(&start), Some(&end)) => (start as usize, end as usize),
+            _ => return,
+        };
+        let entries = self.entries.borrow();
+        let mut requested = self.requested.borrow_mut();
+        for entry in entries.iter().skip(start).take(end + 1 - start) {
+            if requested.insert(entry.album.id) {
+                let _ = self.requests.send(entry.clone());
+            }
+        }
+    }
+
+    /// Decodes the image data of an album's cover into its thumbnail, and shows it if the
+    /// album is in the grid.
+    fn set_thumbnail(&self, id_album: i64, data: Option<Vec<u8>>) {
+        let thumbnail = data.and_then(|data| decode_thumbnail(&data));
+        if let Some(ref thumbnail) = thumbnail {
+            if let Some(tree_iter) = self.rows.borrow().get(&id_album) {
+                self.list_store.set_value(tree_iter, 0, &thumbnail.to_value());
+            }
+        }
+        self.thumbnails.borrow_mut().insert(id_album, thumbnail);
+    }
+}
+
+/// Starts the thread that reads the covers of the requested albums, sending their image data
+/// to the main context. The albums requested last are read first, since they are the ones the
+/// user scrolled to. The thread finishes when the grid is dropped.
+fn spawn_cover_reader(sender: glib::Sender<(i64, Option<Vec<u8>>)>)
+    -> mpsc::Sender<AlbumEntry> {
+    let (requests, receiver) = mpsc::channel::<AlbumEntry>();
+    thread::spawn(move || {
+        while let Ok(entry) = receiver.recv() {
+            let mut pending = vec![entry];
+            while let Some(entry) = pending.pop() {
+                let data = cover::album_cover(&entry);
+                if sender.send((entry.album.id, data)).is_err() {
+                    return;
+                }
+                pending.extend(receiver.try_iter());
+            }
+        }
+    });
+    requests
+}
+
+/// Decodes image data and scales it to fit in a thumbnail, keeping its aspect ratio.
+fn decode_thumbnail(data: &[u8]) -> Option<Pixbuf> {
+    let loader = PixbufLoader::new();
+    loader.write(data).ok()?;
+    loader.close().ok()?;
+    let pixbuf = loader.get_pixbuf()?;
+    let (width, height) = (pixbuf.get_width(), pixbuf.get_height());
+    let scale = THUMBNAIL_SIZE as f64 / width.max(height).max(1) as f64;
+    pixbuf.scale_simple(((width as f64 * scale) as i32).max(1),
+        ((height as f64 * scale) as i32).max(1), InterpType::Bilinear)
+}
+
+/// Returns the label of an album in the grid: its name, performer and year.
+fn album_markup(entry: &AlbumEntry) -> String {
+    let mut details: Vec<String> = entry.performer.iter().cloned().collect();
+    if let Some(year) = entry.album.year {
+        details.push(year.to_string());
+    }
+    format!("<b>{}</b>\n<small>{}</small>", glib::markup_escape_text(&entry.album.name),
+        glib::markup_escape_text(&details.join(" · ")))
+}
+
+/// Creates a column of the tracks' list.
+fn tracks_column(title: &str, num_column: i32, expand: bool) -> gtk::TreeViewColumn {
+    let cell_renderer = gtk::CellRendererText::new();
+    let view_column = gtk::TreeViewColumn::new();
+    view_column.set_expand(expand);
+    view_column.set_title(title);
+    view_column.pack_start(&cell_renderer, true);
+    view_column.add_attribute(&cell_renderer, "text", num_column);
+    view_column
+}
@@ -0,0 +1,880 @@
+use gio::{self, prelude::*};
+use glib;
+use gtk::{self, prelude::*};
+use std::{cell::RefCell, collections::{HashMap, HashSet}, path::{Path, PathBuf}, rc::Rc, thread};
+use music_player_rs::music_manager::{
+    Error,
+    Result,
+    config::Config,
+    miner::{Miner, MinerEvent, MinerHandle, music_file::MusicFile},
+    music_database::{MusicDatabase, SongFilter},
+    search_manager::SearchManager,
+    model::Song,
+};
+use super::preferences;
+use super::statistics;
+use super::album_grid::AlbumGrid;
+use super::browser::Browser;
+use super::columns::{self, SongColumns, ID_COLUMN};
+use super::details::SongDetails;
+use super::duplicates::{self, DuplicatesView};
+use super::history::HistoryView;
+use super::missing::{self, MissingView};
+use super::playlists::PlaylistsView;
+use super::search::{self, SearchBar};
+
+/// The state of the graphical interface: the configuration, the widgets of the window, the rows
+/// of the listed songs, the filters of the songs (the browser's and the search's), the database
+/// connection and the running mining.
+/// The miner's events are received in the main context through a channel, so the state is only
+/// used from the GTK thread.
+pub struct App {
+    config: Config,
+    window: gtk::Window,
+    status_label: gtk::Label,
+    miner_controls: gtk::Box,
+    pause_button: gtk::ToggleButton,
+    search_bar: Rc<SearchBar>,
+    tree_view: gtk::TreeView,
+    columns: Rc<SongColumns>,
+    list_store: gtk::ListStore,
+    song_filter: gtk::TreeModelFilter,
+    browser: Browser,
+    album_grid: Rc<AlbumGrid>,
+    history: HistoryView,
+    playlists: Rc<PlaylistsView>,
+    duplicates: DuplicatesView,
+    missing: MissingView,
+    view_stack: gtk::Stack,
+    browser_filter: RefCell<SongFilter>,
+    browser_ids: RefCell<Option<HashSet<i64>>>,
+    search_manager: Option<RefCell<SearchManager>>,
+    details: SongDetails,
+    rows: RefCell<HashMap<i64, gtk::TreeIter>>,
+    database: Option<MusicDatabase>,
+    mining: RefCell<Option<(MinerHandle, thread::JoinHandle<()>)>>,
+}
+
+impl App {
+
+    /// Creates the window of the application, loads the stored songs and starts mining the
+    /// library. An error reading the configuration or the icon is shown in the status label.
+    pub fn new(application: &gtk::Application, config: Config, config_error: Option<Error>)
+        -> Rc<App> {
+        let music_player_glade = include_str!("../ui/MusicPlayer.glade");
+        let builder = gtk::Builder::new_from_string(music_player_glade);
+        let window: gtk::Window = builder.get_object("MPWindow").unwrap();
+        let album_image: gtk::Image = builder.get_object("AlbumImage").unwrap();
+        let status_label: gtk::Label = builder.get_object("StatusLabel").unwrap();
+        let icon = Path::new("./src/ui/rust_logo.png");
+        if let Err(e) = gtk::Window::set_default_icon_from_file(icon) {
+            status_label.set_text(&format!("Error loading the icon: {}", e));
+        }
+
+        let database = match config.database() {
+            Ok(database) => Some(database),
+            Err(e) => {
+                status_label.set_text(&format!("Error connecting to database: {}", e));
+                None
+            },
+        };
+        let search_manager = config.database().ok()
+            .map(|database| RefCell::new(SearchManager::with_database(database)));
+        if let Some(e) = config_error {
+            status_label.set_text(&format!("Error reading configuration: {}", e));
+        }
+
+        let list_store = gtk::ListStore::new(&columns::store_types());
+        let tree_view: gtk::TreeView = builder.get_object("TreeView").unwrap();
+        let columns_button: gtk::MenuButton = builder.get_object("ColumnsButton").unwrap();
+        let columns = SongColumns::new(&tree_view, &columns_button, &config.view);
+        let song_filter = gtk::TreeModelFilter::new(&list_store, None::<&gtk::TreePath>);
+        let browser_box: gtk::Box = builder.get_object("BrowserBox").unwrap();
+        let album_grid_box: gtk::Box = builder.get_object("AlbumGridBox").unwrap();
+        let history_box: gtk::Box = builder.get_object("HistoryBox").unwrap();
+        let playlists_box: gtk::Box = builder.get_object("PlaylistsBox").unwrap();
+        let duplicates_box: gtk::Box = builder.get_object("DuplicatesBox").unwrap();
+        let missing_box: gtk::Box = builder.get_object("MissingBox").unwrap();
+        let app = Rc::new(App {
+            config: config,
+            window: window,
+            status_label: status_label,
+            miner_controls: builder.get_object("MinerControls").unwrap(),
+            pause_button: builder.get_object("PauseButton").unwrap(),
+            search_bar: SearchBar::from_builder(&builder),
+            tree_view: tree_view,
+            columns: columns,
+            list_store: list_store,
+            song_filter: song_filter,
+            browser: Browser::new(&browser_box),
+            album_grid: AlbumGrid::new(&album_grid_box),
+            history: HistoryView::new(&history_box),
+            playlists: PlaylistsView::new(&playlists_box),
+            duplicates: DuplicatesView::new(&duplicates_box),
+            missing: MissingView::new(&missing_box),
+            view_stack: builder.get_object("ViewStack").unwrap(),
+            browser_filter: RefCell::new(SongFilter::default()),
+            browser_ids: RefCell::new(None),
+            search_manager: search_manager,
+            details: SongDetails::from_builder(&builder),
+            rows: RefCell::new(HashMap::new()),
+            database: database,
+            mining: RefCell::new(None),
+        });
+
+        let app_weak = Rc::downgrade(&app);
+        app.song_filter.set_visible_func(move |tree_model, tree_iter| {
+            match app_weak.upgrade() {
+                Some(app) => app.is_visible(tree_model, tree_iter),
+                None => true,
+            }
+        });
+        let sort_model = gtk::TreeModelSort::new(&app.song_filter);
+        SongColumns::sort(sort_model.upcast_ref(), &app.config.view);
+        app.tree_view.set_model(&sort_model);
+
+        App::connect_signals(&app, &builder);
+        App::add_actions(&app, application);
+
+        album_image.set_from_file(Path::new("./src/ui/music_album.png"));
+        app.window.set_application(Some(application));
+        app.window.maximize();
+        app.window.show_all();
+
+        app.load_songs();
+        app.load_browser();
+        app.load_searches();
+        App::start_mining(&app);
+        app
+    }
+
+    /// Shows the window to the user.
+    pub fn present(&self) {
+        self.window.present();
+    }
+
+    /// Connects the signals of the widgets to the application.
+    fn connect_signals(app: &Rc<App>, builder: &gtk::Builder) {
+        let cancel_button: gtk::Button = builder.get_object("CancelButton").unwrap();
+
+        let app_clone = app.clone();
+        app.tree_view.connect_cursor_changed(move |_| app_clone.show_selected_song());
+
+        let app_clone = app.clone();
+        SearchBar::connect_search(&app.search_bar, move |query, activated| {
+            app_clone.search(query, activated);
+        });
+        let app_clone = app.clone();
+        SearchBar::connect_completions(&app.search_bar, move |query| {
+            app_clone.search_completions(query)
+        });
+
+        let app_clone = app.clone();
+        app.browser.genres.connect_changed(move || {
+            {
+                let mut filter = app_clone.browser_filter.borrow_mut();
+                filter.genre = app_clone.browser.genres.selected();
+                filter.id_performer = None;
+                filter.id_album = None;
+            }
+            app_clone.load_browser();
+        });
+        let app_clone = app.clone();
+        app.browser.performers.connect_changed(move || {
+            {
+                let mut filter = app_clone.browser_filter.borrow_mut();
+                filter.id_performer = app_clone.browser.performers.selected()
+                    .and_then(|id| id.parse().ok());
+                filter.id_album = None;
+            }
+            app_clone.load_browser();
+        });
+        let app_clone = app.clone();
+        app.browser.albums.connect_changed(move || {
+            app_clone.browser_filter.borrow_mut().id_album = app_clone.browser.albums.selected()
+                .and_then(|id| id.parse().ok());
+            app_clone.load_browser();
+        });
+
+        let app_clone = app.clone();
+        app.columns.connect_rating_edited(move |id, rating| app_clone.rate_song(id, rating));
+
+        let app_clone = app.clone();
+        app.view_stack.connect_property_visible_child_name_notify(move |view_stack| {
+            match view_stack.get_visible_child_name().as_ref().map(|name| name.as_str()) {
+                Some("history") => app_clone.load_history(),
+                Some("playlists") => app_clone.load_playlists(None),
+                Some("duplicates") => app_clone.load_duplicates(),
+                Some("missing") => app_clone.load_missing(),
+                _ => {},
+            }
+        });
+        let app_clone = app.clone();
+        app.history.connect_period_changed(move || app_clone.load_history());
+
+        let app_clone = app.clone();
+        app.playlists.connect_selection_changed(move || app_clone.show_playlist());
+        let app_clone = app.clone();
+        app.playlists.connect_save_clicked(move || app_clone.save_playlist());
+        let app_clone = app.clone();
+        app.playlists.connect_delete_clicked(move || app_clone.delete_playlist());
+
+        let app_clone = app.clone();
+        app.duplicates.connect_remove_clicked(move || app_clone.remove_duplicates());
+        let app_clone = app.clone();
+        app.duplicates.connect_hide_clicked(move || app_clone.hide_duplicates());
+
+        let app_clone = app.clone();
+        app.missing.connect_locate_clicked(move || app_clone.locate_missing());
+        let app_clone = app.clone();
+        app.missing.connect_remove_clicked(move || app_clone.remove_missing());
+
+        let app_clone = app.clone();
+        app.album_grid.connect_activated(move |id_album| app_clone.show_album(id_album));
+
+        let app_clone = app.clone();
+        app.pause_button.connect_toggled(move |button| {
+            if let Some((ref handle, _)) = *app_clone.mining.borrow() {
+                if button.get_active() {
+                    handle.pause();
+                }
+                else {
+                    handle.resume();
+                }
+            }
+        });
+        let app_clone = app.clone();
+        cancel_button.connect_clicked(move |_| {
+            if let Some((ref handle, _)) = *app_clone.mining.borrow() {
+                handle.cancel();
+            }
+        });
+    }
+
+    /// Adds the actions of the application and their keyboard shortcuts: quit (Ctrl+Q),
+    /// preferences (Ctrl+,), search (Ctrl+F) and save the search (Ctrl+S), and the actions to
+    /// show the library statistics and to delete a saved search.
+    fn add_actions(app: &Rc<App>, application: &gtk::Application) {
+        let quit = gio::SimpleAction::new("quit", None);
+        let application_clone = application.clone();
+        quit.connect_activate(move |_, _| application_clone.quit());
+        application.add_action(&quit);
+        application.set_accels_for_action("app.quit", &["<Primary>q"]);
+
+        let preferences = gio::SimpleAction::new("preferences", None);
+        let app_clone = app.clone();
+        preferences.connect_activate(move |_, _| app_clone.show_preferences());
+        application.add_action(&preferences);
+        application.set_accels_for_action("app.preferences", &["<Primary>comma"]);
+
+        let statistics = gio::SimpleAction::new("statistics", None);
+        let app_clone = app.clone();
+        statistics.connect_activate(move |_, _| App::show_statistics(&app_clone));
+        application.add_action(&statistics);
+
+        let search = gio::SimpleAction::new("search", None);
+        let app_clone = app.clone();
+        search.connect_activate(move |_, _| app_clone.search_bar.grab_focus());
+        application.add_action(&search);
+        application.set_accels_for_action("app.search", &["<Primary>f"]);
+
+        let save_search = gio::SimpleAction::new("save-search", None);
+        let app_clone = app.clone();
+        save_search.connect_activate(move |_, _| app_clone.save_search());
+        application.add_action(&save_search);
+        application.set_accels_for_action("app.save-search", &["<Primary>s"]);
+
+        let delete_search = gio::SimpleAction::new("delete-search", glib::VariantTy::new("x").ok());
+        let app_clone = app.clone();
+        delete_search.connect_activate(move |_, parameter| {
+            if let Some(id) = parameter.as_ref().and_then(|parameter| parameter.get::<i64>()) {
+                app_clone.delete_search(id);
+            }
+        });
+        application.add_action(&delete_search);
+    }
+
+    /// Starts mining the library in another thread. The miner's events are forwarded to a
+    /// channel of the main context, so every one of them is handled in the GTK thread.
+    fn start_mining(app: &Rc<App>) {
+        let mut miner = match Miner::with_config(&app.config) {
+            Ok(miner) => miner,
+            Err(e) => {
+                app.status_label.set_text(&format!("Error starting miner: {}", e));
+                return;
+            },
+        };
+        let listener = miner.get_listener();
+        let handle = miner.handle();
+        let miner_thread = thread::spawn(move || {
+            if let Err(e) = miner.mine() {
+                error!("Mining failed: {}", e);
+            }
+        });
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        thread::spawn(move || {
+            for event in listener {
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        let app_clone = app.clone();
+        receiver.attach(None, move |event| glib::Continue(app_clone.handle_event(event)));
+
+        app.miner_controls.show_all();
+        *app.mining.borrow_mut() = Some((handle, miner_thread));
+    }
+
+    /// Handles an event of the miner, returning whether more events are expected.
+    fn handle_event(&self, event: MinerEvent) -> bool {
+        let label = &self.status_label;
+        match event {
+            MinerEvent::Ready => label.set_text("Searching songs..."),
+            MinerEvent::Progress(progress) => {
+                label.set_text(&format!("Mining: {}", progress));
+                label.set_tooltip_text(Some(&*progress.path.to_string_lossy()));
+            },
+            MinerEvent::SongAdded(song) | MinerEvent::SongUpdated(song) => self.set_song(&song),
+            MinerEvent::SongRemoved(id) => self.remove_song(id),
+            MinerEvent::Mining => label.set_text("Mining..."),
+            MinerEvent::Paused => label.set_text("Mining paused"),
+            MinerEvent::Finished(summary) => {
+                label.set_text(&format!("Mining finished, {}", summary));
+                label.set_tooltip_text(None);
+                self.miner_controls.hide();
+                self.library_changed();
+                return false;
+            },
+            MinerEvent::Cancelled(summary) => {
+                label.set_text(&format!("Mining cancelled, {}", summary));
+                label.set_tooltip_text(None);
+                self.miner_controls.hide();
+                self.library_changed();
+                return false;
+            },
+            MinerEvent::Failed(message) => {
+                label.set_text(&format!("Mining failed: {}", message));
+                self.miner_controls.hide();
+                return false;
+            },
+            MinerEvent::FileFailed(..) | MinerEvent::DirectorySkipped(..) => {},
+        }
+        true
+    }
+
+    /// Cancels the running mining, waiting for the miner to stop, so the database is not left
+    /// half-written when the application quits, and saves the layout of the songs' list.
+    pub fn shutdown(&self) {
+        if let Some((handle, miner_thread)) = self.mining.borrow_mut().take() {
+            handle.cancel();
+            if miner_thread.join().is_err() {
+                error!("The miner panicked");
+            }
+        }
+        if let Err(e) = self.save_view() {
+            error!("Error saving the layout of the songs: {}", e);
+        }
+    }
+
+    /// Saves the layout of the songs' list to the configuration file, if it changed.
+    fn save_view(&self) -> Result<()> {
+        let view = self.columns.settings();
+        let mut config = Config::read(self.config.file.as_ref().map(PathBuf::as_path))?;
+        if config.view == view {
+            return Ok(());
+        }
+        config.view = view;
+        config.save()
+    }
+
+    /// Loads the songs stored in the database into the list store.
+    fn load_songs(&self) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        match database.songs() {
+            Ok(songs) => for song in songs {
+                self.set_song(&song);
+            },
+            Err(e) => self.status_label.set_text(&format!("Error loading songs: {}", e)),
+        }
+    }
+
+    /// Inserts a song into the list store, or updates its row if the song is already listed.
+    fn set_song(&self, song: &Song) {
+        let list_store = &self.list_store;
+        let tree_iter = self.rows.borrow_mut().entry(song.id)
+            .or_insert_with(|| list_store.append())
+            .clone();
+        let (columns, values) = columns::row_values(song);
+        let data: Vec<&ToValue> = values.iter().map(|value| value as &ToValue).collect();
+        list_store.set(&tree_iter, &columns, &data);
+    }
+
+    /// Sets the rating of a song, updating its row.
+    fn rate_song(&self, id: i64, rating: u8) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        match database.set_rating(id, rating).and_then(|_| database.song(id)) {
+            Ok(Some(song)) => self.set_song(&song),
+            Ok(None) => {},
+            Err(e) => self.status_label.set_text(&format!("Error rating song: {}", e)),
+        }
+    }
+
+    /// Removes the row of a song from the list store, if it is listed.
+    fn remove_song(&self, id: i64) {
+        if let Some(tree_iter) = self.rows.borrow_mut().remove(&id) {
+            self.list_store.remove(&tree_iter);
+        }
+    }
+
+    /// Shows the details of the selected song, looked up in the database by its id.
+    fn show_selected_song(&self) {
+        let tree_selection: gtk::TreeSelection = self.tree_view.get_selection();
+        let id = tree_selection.get_selected().and_then(|(tree_model, tree_iter)| {
+            tree_model.get_value(&tree_iter, ID_COLUMN as i32).get::<i64>()
+        });
+        let song = match (id, &self.database) {
+            (Some(id), &Some(ref database)) => match database.song(id) {
+                Ok(song) => song,
+                Err(e) => {
+                    self.status_label.set_text(&format!("Error loading song: {}", e));
+                    None
+                },
+            },
+            _ => None,
+        };
+        self.details.show(song.as_ref());
+    }
+
+    /// Filters the listed songs with a query of the search manager's language. An empty query
+    /// shows every song. While the user types, an invalid query keeps the previous results;
+    /// when the user presses Enter, it is reported, and a valid query is added to the recent
+    /// searches.
+    fn search(&self, query: &str, activated: bool) {
+        let search_manager = match self.search_manager {
+            Some(ref search_manager) => search_manager,
+            None => return,
+        };
+        if let Err(e) = search_manager.borrow_mut().set_rules(query) {
+            if activated {
+                self.status_label.set_text(&format!("Invalid search: {}", e));
+            }
+            return;
+        }
+        self.song_filter.refilter();
+        if activated {
+            match search_manager.borrow().remember(query) {
+                Ok(()) => self.load_searches(),
+                Err(e) => error!("Error saving the recent search: {}", e),
+            }
+        }
+    }
+
+    /// Returns the completions of the value written after the last prefix of a query.
+    fn search_completions(&self, query: &str) -> Vec<String> {
+        let search_manager = match self.search_manager {
+            Some(ref search_manager) => search_manager.borrow(),
+            None => return Vec::new(),
+        };
+        search_manager.completions(query).unwrap_or_else(|e| {
+            error!("Error completing the search: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Offers the recent and saved searches in the search bar.
+    fn load_searches(&self) {
+        let search_manager = match self.search_manager {
+            Some(ref search_manager) => search_manager.borrow(),
+            None => return,
+        };
+        let searches = search_manager.recent_searches()
+            .and_then(|recent| Ok((recent, search_manager.saved_searches()?)));
+        match searches {
+            Ok((recent, saved)) => self.search_bar.set_searches(&recent, &saved),
+            Err(e) => self.status_label.set_text(&format!("Error loading searches: {}", e)),
+        }
+    }
+
+    /// Asks the user a name for the query of the search bar, and saves it.
+    fn save_search(&self) {
+        let query = self.search_bar.text();
+        if query.trim().is_empty() {
+            self.status_label.set_text("Write a search to save it");
+            return;
+        }
+        let name = match search::ask_name(&self.window, &query) {
+            Some(name) => name,
+            None => return,
+        };
+        let saved = match self.search_manager {
+            Some(ref search_manager) => search_manager.borrow().save_search(&name, &query),
+            None => return,
+        };
+        match saved {
+            Ok(_) => self.load_searches(),
+            Err(e) => self.status_label.set_text(&format!("Error saving search: {}", e)),
+        }
+    }
+
+    /// Deletes a saved search.
+    fn delete_search(&self, id_search: i64) {
+        let deleted = match self.search_manager {
+            Some(ref search_manager) => search_manager.borrow().delete_search(id_search),
+            None => return,
+        };
+        match deleted {
+            Ok(()) => self.load_searches(),
+            Err(e) => self.status_label.set_text(&format!("Error deleting search: {}", e)),
+        }
+    }
+
+    /// Fills the browser's panes and the album grid with the genres, performers and albums of
+    /// the songs that match the browser's filter, and filters the listed songs with it.
+    fn load_browser(&self) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        let filter = self.browser_filter.borrow().clone();
+        let result = database.genre_counts().and_then(|genres| {
+            let rows = genres.into_iter()
+                .map(|(genre, count)| (genre.clone(), genre, count))
+                .collect();
+            self.browser.genres.set_rows(rows, filter.genre.as_ref().map(String::as_str));
+            database.performer_counts(&filter)
+        }).and_then(|performers| {
+            let rows = performers.into_iter()
+                .map(|(performer, count)| (performer.id.to_string(), performer.name, count))
+                .collect();
+            let selected = filter.id_performer.map(|id| id.to_string());
+            self.browser.performers.set_rows(rows, selected.as_ref().map(String::as_str));
+            database.album_counts(&filter)
+        }).and_then(|albums| {
+            let rows = albums.into_iter()
+                .map(|(album, count)| (album.id.to_string(), album.name, count))
+                .collect();
+            let selected = filter.id_album.map(|id| id.to_string());
+            self.browser.albums.set_rows(rows, selected.as_ref().map(String::as_str));
+            database.album_entries(&filter)
+        }).and_then(|entries| {
+            AlbumGrid::set_albums(&self.album_grid, entries);
+            if filter == SongFilter::default() {
+                return Ok(None);
+            }
+            database.song_ids(&filter).map(|ids| Some(ids.into_iter().collect()))
+        });
+        match result {
+            Ok(ids) => *self.browser_ids.borrow_mut() = ids,
+            Err(e) => self.status_label.set_text(&format!("Error browsing songs: {}", e)),
+        }
+        self.song_filter.refilter();
+    }
+
+    /// Shows the tracks of an album in the album grid, ordered by disc and track number.
+    fn show_album(&self, id_album: i64) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        match database.album_songs(id_album) {
+            Ok(songs) => {
+                let album = songs.first().map(|song| song.album.name.clone()).unwrap_or_default();
+                self.album_grid.set_tracks(&album, &songs);
+            },
+            Err(e) => self.status_label.set_text(&format!("Error loading album: {}", e)),
+        }
+    }
+
+    /// Fills the history view with the listening history of its period.
+    fn load_history(&self) {
+        if let Some(ref database) = self.database {
+            if let Err(e) = self.history.load(database) {
+                self.status_label.set_text(&format!("Error loading history: {}", e));
+            }
+        }
+    }
+
+    /// Lists the smart playlists, selecting the one with the given name, or the selected one if
+    /// there is none, and shows its songs.
+    fn load_playlists(&self, selected: Option<&str>) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        let selected = selected.map(str::to_owned)
+            .or_else(|| self.playlists.selected().map(|playlist| playlist.name));
+        match database.playlists() {
+            Ok(playlists) => self.playlists.set_playlists(playlists,
+                selected.as_ref().map(String::as_str)),
+            Err(e) => self.status_label.set_text(&format!("Error loading playlists: {}", e)),
+        }
+        self.show_playlist();
+    }
+
+    /// Shows the songs of the selected smart playlist, evaluating its rules against the current
+    /// library.
+    fn show_playlist(&self) {
+        let (search_manager, playlist) = match (&self.search_manager, self.playlists.selected()) {
+            (&Some(ref search_manager), Some(playlist)) => (search_manager, playlist),
+            _ => {
+                self.playlists.set_songs(&[]);
+                return;
+            },
+        };
+        let songs = search_manager.borrow().playlist_songs(&playlist);
+        match songs {
+            Ok(songs) => self.playlists.set_songs(&songs),
+            Err(e) => {
+                self.playlists.set_songs(&[]);
+                self.status_label.set_text(&format!("Error loading playlist: {}", e));
+            },
+        }
+    }
+
+    /// Saves the smart playlist of the editor, replacing the one with the same name.
+    fn save_playlist(&self) {
+        let search_manager = match self.search_manager {
+            Some(ref search_manager) => search_manager,
+            None => return,
+        };
+        let saved = self.playlists.edited().and_then(|playlist| {
+            search_manager.borrow().save_playlist(&playlist)?;
+            Ok(playlist.name)
+        });
+        match saved {
+            Ok(name) => self.load_playlists(Some(&name)),
+            Err(e) => self.status_label.set_text(&format!("Error saving playlist: {}", e)),
+        }
+    }
+
+    /// Deletes the selected smart playlist.
+    fn delete_playlist(&self) {
+        let (database, playlist) = match (&self.database, self.playlists.selected()) {
+            (&Some(ref database), Some(playlist)) => (database, playlist),
+            _ => return,
+        };
+        if let Err(e) = database.delete_playlist(playlist.id) {
+            self.status_label.set_text(&format!("Error deleting playlist: {}", e));
+        }
+        self.load_playlists(None);
+    }
+
+    /// Lists the songs that are likely duplicates.
+    fn load_duplicates(&self) {
+        if let Some(ref database) = self.database {
+            if let Err(e) = self.duplicates.load(database) {
+                self.status_label.set_text(&format!("Error finding duplicates: {}", e));
+            }
+        }
+    }
+
+    /// Keeps the selected duplicate and moves the files of the other songs of its group to the
+    /// trash, once the user confirms it. Those songs are merged into the kept one first, so their
+    /// ratings, counts and playbacks are not lost, and no file is trashed if they cannot be
+    /// merged. A file that
+    /// cannot be trashed is added again the next time the library is mined.
+    fn remove_duplicates(&self) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        let (kept, others) = match self.duplicates.selected() {
+            Some(selected) => selected,
+            None => return,
+        };
+        if !duplicates::confirm_trash(&self.window, &others) {
+            return;
+        }
+        let ids: Vec<i64> = others.iter().map(|song| song.id).collect();
+        if let Err(e) = database.merge_songs(kept.id, &ids) {
+            self.status_label.set_text(&format!("Error removing duplicates: {}", e));
+            return;
+        }
+        self.duplicates_removed(&kept, &ids);
+        for song in others {
+            let file = gio::File::new_for_path(&song.path);
+            if let Err(e) = file.trash(None::<&gio::Cancellable>) {
+                self.status_label.set_text(&format!("Error moving {:?} to the trash: {}",
+                    song.path, e));
+            }
+        }
+    }
+
+    /// Keeps the selected duplicate and hides the other songs of its group.
+    fn hide_duplicates(&self) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        let (kept, others) = match self.duplicates.selected() {
+            Some(selected) => selected,
+            None => return,
+        };
+        let mut hidden = Vec::new();
+        for song in others {
+            match database.set_hidden(song.id, true) {
+                Ok(()) => hidden.push(song.id),
+                Err(e) => self.status_label.set_text(&format!("Error hiding duplicate: {}", e)),
+            }
+        }
+        self.duplicates_removed(&kept, &hidden);
+    }
+
+    /// Removes the songs that are no longer listed after reviewing duplicates, shows the kept
+    /// song again, as its statistics may have changed, and lists the remaining duplicates.
+    fn duplicates_removed(&self, kept: &Song, ids: &[i64]) {
+        for id in ids {
+            self.remove_song(*id);
+        }
+        if let Some(ref database) = self.database {
+            if let Ok(Some(song)) = database.song(kept.id) {
+                self.set_song(&song);
+            }
+        }
+        if !ids.is_empty() {
+            self.status_label.set_text(&format!("Kept {:?}, {} other copies removed from the list",
+                kept.title, ids.len()));
+        }
+        self.load_browser();
+        self.load_duplicates();
+    }
+
+    /// Lists the songs whose file is missing.
+    fn load_missing(&self) {
+        if let Some(ref database) = self.database {
+            if let Err(e) = self.missing.load(database) {
+                self.status_label.set_text(&format!("Error loading missing songs: {}", e));
+            }
+        }
+    }
+
+    /// Asks the user the new file of the selected missing song, and moves the song to it. The
+    /// song keeps its id, rating, counts and playbacks, and is listed again; if the file was
+    /// already stored as another song, that song is no longer listed.
+    fn locate_missing(&self) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        let song = match self.missing.selected() {
+            Some(song) => song,
+            None => return,
+        };
+        let path = match missing::choose_file(&self.window, &song) {
+            Some(path) => path,
+            None => return,
+        };
+        let replaced = database.song_id(&path.to_string_lossy());
+        let relocated = MusicFile::from_path(path)
+            .and_then(|mut file| {
+                file.hash_audio();
+                database.relocate_song(song.id, file)
+            })
+            .and_then(|()| database.song(song.id));
+        match relocated {
+            Ok(Some(relocated)) => {
+                if let Ok(Some(id)) = replaced {
+                    self.remove_song(id);
+                }
+                self.set_song(&relocated);
+                self.status_label.set_text(&format!("{:?} moved to {:?}", relocated.title,
+                    relocated.path));
+                self.load_browser();
+            },
+            Ok(None) => {},
+            Err(e) => self.status_label.set_text(&format!("Error locating {:?}: {}",
+                song.title, e)),
+        }
+        self.load_missing();
+    }
+
+    /// Removes the selected missing song from the library.
+    fn remove_missing(&self) {
+        let database = match self.database {
+            Some(ref database) => database,
+            None => return,
+        };
+        if let Some(song) = self.missing.selected() {
+            if let Err(e) = database.delete_song(song.id) {
+                self.status_label.set_text(&format!("Error removing {:?}: {}", song.title, e));
+            }
+        }
+        self.load_missing();
+    }
+
+    /// Refreshes what depends on the songs of the library after it was mined: the search, the
+    /// browser and, if they are shown, the smart playlists, the duplicates and the missing
+    /// songs, which may have changed.
+    fn library_changed(&self) {
+        self.search(&self.search_bar.text(), false);
+        self.load_browser();
+        let visible = self.view_stack.get_visible_child_name();
+        match visible.as_ref().map(|name| name.as_str()) {
+            Some("playlists") => self.load_playlists(None),
+            Some("duplicates") => self.load_duplicates(),
+            Some("missing") => self.load_missing(),
+            _ => {},
+        }
+    }
+
+    /// Checks if a row of the songs' list store matches the browser's filter and the search.
+    fn is_visible(&self, tree_model: &gtk::TreeModel, tree_iter: &gtk::TreeIter) -> bool {
+        let id = match tree_model.get_value(tree_iter, ID_COLUMN as i32).get::<i64>() {
+            Some(id) => id,
+            None => return false,
+        };
+        if let Some(ref ids) = *self.browser_ids.borrow() {
+            if !ids.contains(&id) {
+                return false;
+            }
+        }
+        match self.search_manager {
+            Some(ref search_manager) => search_manager.borrow().is_visible(id),
+            None => true,
+        }
+    }
+
+    /// Shows the statistics of the library. They are read in another thread, with its own
+    /// connection to the database, since the size and the cover art are read from the files.
+    fn show_statistics(app: &Rc<App>) {
+        if app.database.is_none() {
+            return;
+        }
+        app.status_label.set_text("Reading statistics...");
+        let config = app.config.clone();
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        thread::spawn(move || {
+            let result = config.database().and_then(|database| database.statistics());
+            let _ = sender.send(result.map_err(|e| e.to_string()));
+        });
+        let app_clone = app.clone();
+        receiver.attach(None, move |result| {
+            match result {
+                Ok(library_statistics) => {
+                    app_clone.status_label.set_text("");
+                    statistics::run(&app_clone.window, &library_statistics);
+                },
+                Err(e) => app_clone.status_label
+                    .set_text(&format!("Error reading statistics: {}", e)),
+            }
+            glib::Continue(false)
+        });
+    }
+
+    /// Shows the preferences dialog.
+    fn show_preferences(&self) {
+        match preferences::run(&self.window, self.config.file.as_ref().map(PathBuf::as_path)) {
+            Ok(true) => self.status_label.set_text("Preferences saved, they apply on the next start"),
+            Ok(false) => {},
+            Err(e) => self.status_label.set_text(&format!("Error saving preferences: {}", e)),
+        }
+    }
+}
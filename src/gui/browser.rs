@@ -0,0 +1,106 @@
+use gtk::{self, prelude::*};
+use std::{cell::Cell, rc::Rc};
+
+/// A column browser with three panes: genres, performers and albums. Every pane lists its
+/// values with their number of songs, after an "All" row that selects every value.
+pub struct Browser {
+    pub genres: Pane,
+    pub performers: Pane,
+    pub albums: Pane,
+}
+
+impl Browser {
+
+    /// Creates the panes of the browser, packing them into a container.
+    pub fn new(container: &gtk::Box) -> Browser {
+        Browser {
+            genres: Pane::new("Genre", container),
+            performers: Pane::new("Performer", container),
+            albums: Pane::new("Album", container),
+        }
+    }
+}
+
+/// A pane of the column browser. Every row has a key that identifies its value (the genre, or
+/// the id of the performer or album), a name and a number of songs.
+pub struct Pane {
+    list_store: gtk::ListStore,
+    tree_view: gtk::TreeView,
+    updating: Rc<Cell<bool>>,
+}
+
+impl Pane {
+
+    /// Creates a pane with a title, packing it into a container.
+    fn new(title: &str, container: &gtk::Box) -> Pane {
+        let list_store = gtk::ListStore::new(&[gtk::Type::String, gtk::Type::I64,
+            gtk::Type::String]);
+        let tree_view = gtk::TreeView::new_with_model(&list_store);
+        tree_view.append_column(&pane_column(title, 0, true));
+        tree_view.append_column(&pane_column("Songs", 1, false));
+        let scrolled_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        scrolled_window.set_shadow_type(gtk::ShadowType::In);
+        scrolled_window.add(&tree_view);
+        container.pack_start(&scrolled_window, true, true, 0);
+        Pane {
+            list_store: list_store,
+            tree_view: tree_view,
+            updating: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Replaces the rows of the pane with the given keys, names and numbers of songs. The row
+    /// with the selected key is selected again; if there is none, the "All" row is selected.
+    /// The change of the selection is not notified.
+    pub fn set_rows(&self, rows: Vec<(String, String, i64)>, selected: Option<&str>) {
+        self.updating.set(true);
+        self.list_store.clear();
+        let total: i64 = rows.iter().map(|&(_, _, count)| count).sum();
+        let all = format!("All ({})", rows.len());
+        let first = self.list_store.insert_with_values(None, &[0, 1, 2],
+            &[&all as &ToValue, &total as &ToValue, &String::new() as &ToValue]);
+        let mut selected_iter = first;
+        for (key, name, count) in rows {
+            let tree_iter = self.list_store.insert_with_values(None, &[0, 1, 2],
+                &[&name as &ToValue, &count as &ToValue, &key as &ToValue]);
+            if selected == Some(key.as_str()) {
+                selected_iter = tree_iter;
+            }
+        }
+        self.tree_view.get_selection().select_iter(&selected_iter);
+        self.updating.set(false);
+    }
+
+    /// Returns the key of the selected row, or None if every value is selected.
+    pub fn selected(&self) -> Option<String> {
+        let (tree_model, tree_iter) = self.tree_view.get_selection().get_selected()?;
+        let is_all = tree_model.get_path(&tree_iter)
+            .map_or(true, |tree_path| tree_path.get_indices() == [0]);
+        if is_all {
+            return None;
+        }
+        tree_model.get_value(&tree_iter, 2).get::<String>()
+    }
+
+    /// Calls a function when the user selects another row.
+    pub fn connect_changed<F: Fn() + 'static>(&self, function: F) {
+        let updating = self.updating.clone();
+        self.tree_view.get_selection().connect_changed(move |_| {
+            if !updating.get() {
+                function();
+            }
+        });
+    }
+}
+
+/// Creates a column of a pane.
+fn pane_column(title: &str, num_column: i32, expand: bool) -> gtk::TreeViewColumn {
+    let cell_renderer = gtk::CellRendererText::new();
+    let view_column = gtk::TreeViewColumn::new();
+    view_column.set_expand(expand);
+    view_column.set_title(title);
+    view_column.pack_start(&cell_renderer, true);
+    view_column.add_attribute(&cell_renderer, "text", num_column);
+    view_column
+}
@@ -0,0 +1,194 @@
+use glib;
+use gtk::{self, prelude::*};
+use std::rc::Rc;
+use music_player_rs::music_manager::{
+    config::{ColumnSettings, ViewSettings},
+    model::{Song, SongField},
+};
+
+/// Column of the songs' list store with the id of the song. It is followed by two columns for
+/// every field: its text and the key it is sorted by.
+pub const ID_COLUMN: u32 = 0;
+
+/// Returns the types of the columns of the songs' list store.
+pub fn store_types() -> Vec<gtk::Type> {
+    let mut types = vec![gtk::Type::I64];
+    for field in SongField::ALL.iter() {
+        types.push(gtk::Type::String);
+        types.push(if field.is_numeric() { gtk::Type::I64 } else { gtk::Type::String });
+    }
+    types
+}
+
+/// Returns the column of the songs' list store with the text of a field.
+pub fn text_column(field: SongField) -> u32 {
+    let index = SongField::ALL.iter().position(|other| *other == field).unwrap_or(0);
+    1 + 2 * index as u32
+}
+
+/// Returns the column of the songs' list store with the sort key of a field.
+pub fn key_column(field: SongField) -> u32 {
+    text_column(field) + 1
+}
+
+/// Returns the columns of the songs' list store and the values of a song for them.
+pub fn row_values(song: &Song) -> (Vec<u32>, Vec<glib::Value>) {
+    let mut columns = vec![ID_COLUMN];
+    let mut values = vec![song.id.to_value()];
+    for field in SongField::ALL.iter() {
+        let text = field.text(song);
+        let key = if field.is_numeric() {
+            field.number(song).unwrap_or(-1).to_value()
+        } else {
+            text.to_lowercase().to_value()
+        };
+        columns.push(text_column(*field));
+        values.push(text.to_value());
+        columns.push(key_column(*field));
+        values.push(key);
+    }
+    (columns, values)
+}
+
+/// The columns of the songs' list: one for every field, hidden unless it is in the view
+/// settings. The user chooses the visible columns in a menu, and reorders, resizes and sorts
+/// them in the tree view's header.
+pub struct SongColumns {
+    tree_view: gtk::TreeView,
+    columns: Vec<(SongField, gtk::TreeViewColumn, gtk::CellRendererText)>,
+}
+
+impl SongColumns {
+
+    /// Creates the columns of a tree view with the given layout, and fills a menu with a check
+    /// item to show or hide each of them.
+    pub fn new(tree_view: &gtk::TreeView, menu_button: &gtk::MenuButton,
+        settings: &ViewSettings) -> Rc<SongColumns> {
+        let mut fields: Vec<SongField> = Vec::new();
+        let configured = settings.columns.iter().map(|column| column.field);
+        for field in configured.chain(SongField::ALL.iter().cloned()) {
+            if !fields.contains(&field) {
+                fields.push(field);
+            }
+        }
+        let menu = gtk::Menu::new();
+        let columns = fields.into_iter().map(|field| {
+            let width = settings.columns.iter().find(|column| column.field == field)
+                .map(|column| column.width);
+            let (view_column, cell_renderer) = create_treeview_column(field, width);
+            tree_view.append_column(&view_column);
+
+            let item = gtk::CheckMenuItem::new_with_label(field.title());
+            item.set_active(view_column.get_visible());
+            let view_column_clone = view_column.clone();
+            item.connect_toggled(move |item| view_column_clone.set_visible(item.get_active()));
+            menu.append(&item);
+            (field, view_column, cell_renderer)
+        }).collect();
+        menu.show_all();
+        menu_button.set_popup(Some(&menu));
+
+        Rc::new(SongColumns {
+            tree_view: tree_view.clone(),
+            columns: columns,
+        })
+    }
+
+    /// Sorts a model of the songs' list store as the view settings say.
+    pub fn sort(sortable: &gtk::TreeSortable, settings: &ViewSettings) {
+        if let Some(field) = settings.sort {
+            let order = if settings.sort_descending {
+                gtk::SortType::Descending
+            } else {
+                gtk::SortType::Ascending
+            };
+            sortable.set_sort_column_id(gtk::SortColumn::Index(key_column(field)), order);
+        }
+    }
+
+    /// Makes the ratings editable, calling a function with the id of the song and its new
+    /// rating when the user edits one. A rating is written as a number or as stars.
+    pub fn connect_rating_edited<F: Fn(i64, u8) + 'static>(&self, function: F) {
+        let rating_column = self.columns.iter()
+            .find(|&&(field, _, _)| field == SongField::Rating);
+        let cell_renderer = match rating_column {
+            Some(&(_, _, ref cell_renderer)) => cell_renderer,
+            None => return,
+        };
+        cell_renderer.set_property_editable(true);
+        let tree_view = self.tree_view.clone();
+        cell_renderer.connect_edited(move |_, tree_path, text| {
+            let text = text.trim();
+            let rating = if text.chars().all(|character| character == '★') {
+                Some(text.chars().count() as u8)
+            } else {
+                text.parse::<u8>().ok()
+            };
+            let model = tree_view.get_model();
+            let id = model.as_ref().and_then(|model| {
+                let tree_iter = model.get_iter(&tree_path)?;
+                model.get_value(&tree_iter, ID_COLUMN as i32).get::<i64>()
+            });
+            if let (Some(id), Some(rating)) = (id, rating) {
+                function(id, rating);
+            }
+        });
+    }
+
+    /// Returns the current layout of the columns: the visible ones in the tree view's order with
+    /// their widths, and the sort order of the tree view's model.
+    pub fn settings(&self) -> ViewSettings {
+        let columns = self.tree_view.get_columns().iter()
+            .filter(|view_column| view_column.get_visible())
+            .filter_map(|view_column| {
+                self.columns.iter().find(|&&(_, ref other, _)| other == view_column)
+                    .map(|&(field, _, _)| ColumnSettings {
+                        field: field,
+                        width: view_column.get_width(),
+                    })
+            })
+            .collect();
+        let sort = self.tree_view.get_model()
+            .and_then(|model| model.dynamic_cast::<gtk::TreeSortable>().ok())
+            .and_then(|sortable| sortable.get_sort_column_id());
+        let (sort, sort_descending) = match sort {
+            Some((gtk::SortColumn::Index(column), order)) => {
+                let field = SongField::ALL.iter().find(|field| key_column(**field) == column)
+                    .cloned();
+                (field, order == gtk::SortType::Descending)
+            },
+            _ => (None, false),
+        };
+        ViewSettings {
+            sort: sort,
+            sort_descending: sort_descending,
+            columns: columns,
+        }
+    }
+}
+
+/// Creates a reorderable, resizable and sortable column of a field, with its cell renderer. The
+/// column is visible if it has a width, which is fixed unless it is 0.
+fn create_treeview_column(field: SongField, width: Option<i32>)
+    -> (gtk::TreeViewColumn, gtk::CellRendererText) {
+    let cell_renderer = gtk::CellRendererText::new();
+    if field.is_numeric() {
+        cell_renderer.set_property_xalign(1.0);
+    }
+    let view_column = gtk::TreeViewColumn::new();
+    view_column.set_title(field.title());
+    view_column.set_visible(width.is_some());
+    view_column.set_reorderable(true);
+    view_column.set_resizable(true);
+    match width {
+        Some(width) if width > 0 => {
+            view_column.set_sizing(gtk::TreeViewColumnSizing::Fixed);
+            view_column.set_fixed_width(width);
+        },
+        _ => view_column.set_expand(!field.is_numeric()),
+    }
+    view_column.pack_start(&cell_renderer, true);
+    view_column.add_attribute(&cell_renderer, "text", text_column(field) as i32);
+    view_column.set_sort_column_id(key_column(field) as i32);
+    (view_column, cell_renderer)
+}
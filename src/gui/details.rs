@@ -0,0 +1,79 @@
+use gtk::{self, prelude::*};
+use music_player_rs::music_manager::model::{Song, format_duration};
+
+/// The details of a song shown in the footer: its title, album and performer, and a grid with
+/// its year, track, genre, duration, bitrate, format and file.
+pub struct SongDetails {
+    title: gtk::Label,
+    album: gtk::Label,
+    artist: gtk::Label,
+    year: gtk::Label,
+    track: gtk::Label,
+    genre: gtk::Label,
+    duration: gtk::Label,
+    bitrate: gtk::Label,
+    format: gtk::Label,
+    path: gtk::Label,
+}
+
+impl SongDetails {
+
+    /// Gets the labels of the footer from the builder of the window.
+    pub fn from_builder(builder: &gtk::Builder) -> SongDetails {
+        SongDetails {
+            title: builder.get_object("Title").unwrap(),
+            album: builder.get_object("Album").unwrap(),
+            artist: builder.get_object("Artist").unwrap(),
+            year: builder.get_object("YearValue").unwrap(),
+            track: builder.get_object("TrackValue").unwrap(),
+            genre: builder.get_object("GenreValue").unwrap(),
+            duration: builder.get_object("DurationValue").unwrap(),
+            bitrate: builder.get_object("BitrateValue").unwrap(),
+            format: builder.get_object("FormatValue").unwrap(),
+            path: builder.get_object("PathValue").unwrap(),
+        }
+    }
+
+    /// Shows the details of a song, or clears them if there is none.
+    pub fn show(&self, song: Option<&Song>) {
+        let song = match song {
+            Some(song) => song,
+            None => {
+                self.title.set_text("Title");
+                self.album.set_text("Album");
+                self.artist.set_text("Artist");
+                for label in &[&self.year, &self.track, &self.genre, &self.duration,
+                    &self.bitrate, &self.format, &self.path] {
+                    label.set_text("");
+                    label.set_tooltip_text(None);
+                }
+                return;
+            },
+        };
+        self.title.set_text(&song.title);
+        self.album.set_text(&song.album.name);
+        self.artist.set_text(&song.performer.name);
+        set_optional(&self.year, song.year.map(|year| year.to_string()));
+        let track = match (song.track, song.disc) {
+            (Some(track), Some(disc)) => Some(format!("{} (disc {})", track, disc)),
+            (Some(track), None) => Some(track.to_string()),
+            (None, _) => None,
+        };
+        set_optional(&self.track, track);
+        set_optional(&self.genre, song.genre.clone());
+        set_optional(&self.duration, song.duration.map(format_duration));
+        set_optional(&self.bitrate, song.bitrate.map(|bitrate| format!("{} kbit/s", bitrate)));
+        set_optional(&self.format, song.format());
+        let path = song.path.to_string_lossy();
+        self.path.set_text(&path);
+        self.path.set_tooltip_text(Some(&path));
+    }
+}
+
+/// Sets the text of a label to a value, or to "Unknown" if there is none.
+fn set_optional(label: &gtk::Label, value: Option<String>) {
+    match value {
+        Some(value) => label.set_text(&value),
+        None => label.set_text("Unknown"),
+    }
+}
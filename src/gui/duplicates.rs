@@ -0,0 +1,159 @@
+use gtk::{self, prelude::*, ResponseType};
+use std::{cell::RefCell, time::Duration};
+use music_player_rs::music_manager::{
+    Result,
+    model::{DuplicateGroup, Song, format_duration},
+    music_database::{MusicDatabase, DUPLICATE_TOLERANCE},
+};
+
+/// Column of the tree store with the index of the row's group.
+const GROUP_COLUMN: u32 = 5;
+
+/// Column of the tree store with the index of the row's song in its group, or -1 in the row of
+/// the group itself.
+const SONG_COLUMN: u32 = 6;
+
+/// The review of the songs that are likely duplicates: a row for every group, with its songs
+/// under it. The user selects the song of a group to keep, and removes or hides the others.
+pub struct DuplicatesView {
+    tree_store: gtk::TreeStore,
+    tree_view: gtk::TreeView,
+    remove_button: gtk::Button,
+    hide_button: gtk::Button,
+    groups: RefCell<Vec<DuplicateGroup>>,
+}
+
+impl DuplicatesView {
+
+    /// Creates the tree of duplicates and its buttons, packing them into a container.
+    pub fn new(container: &gtk::Box) -> DuplicatesView {
+        let tree_store = gtk::TreeStore::new(&[gtk::Type::String, gtk::Type::String,
+            gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::U32,
+            gtk::Type::I32]);
+        let tree_view = gtk::TreeView::new_with_model(&tree_store);
+        for (num_column, title) in ["Title", "Performer", "Album", "Length", "Path"].iter()
+            .enumerate() {
+            let cell_renderer = gtk::CellRendererText::new();
+            let view_column = gtk::TreeViewColumn::new();
+            view_column.set_title(title);
+            view_column.set_expand(num_column != 3);
+            view_column.pack_start(&cell_renderer, true);
+            view_column.add_attribute(&cell_renderer, "text", num_column as i32);
+            tree_view.append_column(&view_column);
+        }
+        let scrolled_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        scrolled_window.set_shadow_type(gtk::ShadowType::In);
+        scrolled_window.add(&tree_view);
+        container.pack_start(&scrolled_window, true, true, 0);
+
+        let remove_button = gtk::Button::new_with_label("Keep selected, move others to trash");
+        let hide_button = gtk::Button::new_with_label("Keep selected, hide others");
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.set_halign(gtk::Align::End);
+        buttons.pack_start(&hide_button, false, false, 0);
+        buttons.pack_start(&remove_button, false, false, 0);
+        container.pack_start(&buttons, false, true, 3);
+
+        let view = DuplicatesView {
+            tree_store: tree_store,
+            tree_view: tree_view,
+            remove_button: remove_button,
+            hide_button: hide_button,
+            groups: RefCell::new(Vec::new()),
+        };
+        view.set_buttons_sensitive(false);
+        let remove_button = view.remove_button.clone();
+        let hide_button = view.hide_button.clone();
+        view.tree_view.get_selection().connect_changed(move |selection| {
+            let is_song = selection.get_selected()
+                .and_then(|(tree_model, tree_iter)| {
+                    tree_model.get_value(&tree_iter, SONG_COLUMN as i32).get::<i32>()
+                })
+                .map_or(false, |song| song >= 0);
+            remove_button.set_sensitive(is_song);
+            hide_button.set_sensitive(is_song);
+        });
+        view
+    }
+
+    /// Fills the tree with the groups of songs that are likely duplicates.
+    pub fn load(&self, database: &MusicDatabase) -> Result<()> {
+        let groups = database.duplicates(Duration::from_secs(DUPLICATE_TOLERANCE))?;
+        self.tree_store.clear();
+        for (num_group, group) in groups.iter().enumerate() {
+            let title = format!("{} ({} songs)", group.reason.title(), group.songs.len());
+            let group_iter = self.tree_store.insert_with_values(None, None,
+                &[0, GROUP_COLUMN, SONG_COLUMN],
+                &[&title as &ToValue, &(num_group as u32) as &ToValue, &-1i32 as &ToValue]);
+            for (num_song, song) in group.songs.iter().enumerate() {
+                self.tree_store.insert_with_values(Some(&group_iter), None,
+                    &[0, 1, 2, 3, 4, GROUP_COLUMN, SONG_COLUMN], &[
+                    &song.title as &ToValue,
+                    &song.performer.name as &ToValue,
+                    &song.album.name as &ToValue,
+                    &song.duration.map(format_duration).unwrap_or_default() as &ToValue,
+                    &song.path.to_string_lossy().into_owned() as &ToValue,
+                    &(num_group as u32) as &ToValue,
+                    &(num_song as i32) as &ToValue,
+                ]);
+            }
+        }
+        *self.groups.borrow_mut() = groups;
+        self.tree_view.expand_all();
+        self.set_buttons_sensitive(false);
+        Ok(())
+    }
+
+    /// Returns the selected song, which is kept, and the other songs of its group.
+    pub fn selected(&self) -> Option<(Song, Vec<Song>)> {
+        let (tree_model, tree_iter) = self.tree_view.get_selection().get_selected()?;
+        let num_group = tree_model.get_value(&tree_iter, GROUP_COLUMN as i32).get::<u32>()?;
+        let num_song = tree_model.get_value(&tree_iter, SONG_COLUMN as i32).get::<i32>()?;
+        if num_song < 0 {
+            return None;
+        }
+        let groups = self.groups.borrow();
+        let group = groups.get(num_group as usize)?;
+        let kept = group.songs.get(num_song as usize)?.clone();
+        let others = group.songs.iter().filter(|song| song.id != kept.id).cloned().collect();
+        Some((kept, others))
+    }
+
+    /// Calls a function when the user removes the other songs of the selected song's group.
+    pub fn connect_remove_clicked<F: Fn() + 'static>(&self, function: F) {
+        self.remove_button.connect_clicked(move |_| function());
+    }
+
+    /// Calls a function when the user hides the other songs of the selected song's group.
+    pub fn connect_hide_clicked<F: Fn() + 'static>(&self, function: F) {
+        self.hide_button.connect_clicked(move |_| function());
+    }
+
+    /// Enables or disables the buttons that keep the selected song.
+    fn set_buttons_sensitive(&self, sensitive: bool) {
+        self.remove_button.set_sensitive(sensitive);
+        self.hide_button.set_sensitive(sensitive);
+    }
+}
+
+/// Asks the user to confirm that the files of some songs are moved to the trash, listing them.
+/// Returns whether the user confirmed it.
+pub fn confirm_trash<W: IsA<gtk::Window>>(parent: &W, songs: &[Song]) -> bool {
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        &format!("Move {} files to the trash?", songs.len()));
+    let paths: Vec<String> = songs.iter()
+        .map(|song| song.path.to_string_lossy().into_owned())
+        .collect();
+    dialog.set_property_secondary_text(Some(paths.join("\n").as_str()));
+    dialog.add_button("_Cancel", ResponseType::Cancel.into());
+    dialog.add_button("_Move to trash", ResponseType::Accept.into());
+    dialog.set_default_response(ResponseType::Cancel.into());
+    let response = dialog.run();
+    dialog.destroy();
+    response == ResponseType::Accept.into()
+}
@@ -0,0 +1,133 @@
+use gtk::{self, prelude::*};
+use music_player_rs::music_manager::{
+    Result,
+    model::{Period, format_date, format_duration, now},
+    music_database::MusicDatabase,
+};
+
+/// Periods offered by the history view: their ids, as parsed by `Period::parse`, and labels.
+const PERIODS: [(&str, &str); 4] = [
+    ("today", "Today"),
+    ("this-month", "This month"),
+    ("this-year", "This year"),
+    ("all", "All time"),
+];
+
+/// Maximum number of rows of the playbacks' list and of the most played lists.
+const HISTORY_LIMIT: i64 = 200;
+
+/// The listening history of a period: its playbacks, its most played performers and albums, and
+/// its listening per day.
+pub struct HistoryView {
+    period: gtk::ComboBoxText,
+    playbacks: gtk::ListStore,
+    performers: gtk::ListStore,
+    albums: gtk::ListStore,
+    timeline: gtk::ListStore,
+}
+
+impl HistoryView {
+
+    /// Creates the lists of the history, packing them into a container.
+    pub fn new(container: &gtk::Box) -> HistoryView {
+        let period = gtk::ComboBoxText::new();
+        for &(id, label) in PERIODS.iter() {
+            period.append(Some(id), label);
+        }
+        period.set_active(Some(1));
+        let period_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        period_box.pack_start(&gtk::Label::new(Some("Period")), false, false, 3);
+        period_box.pack_start(&period, false, false, 0);
+        container.pack_start(&period_box, false, true, 3);
+
+        let grid = gtk::Grid::new();
+        grid.set_row_homogeneous(true);
+        grid.set_column_homogeneous(true);
+        grid.set_row_spacing(3);
+        grid.set_column_spacing(3);
+        let playbacks = history_list(&grid, (0, 0),
+            &["Day", "Title", "Performer", "Listened", "Completed"]);
+        let timeline = history_list(&grid, (1, 0), &["Day", "Playbacks", "Listened"]);
+        let performers = history_list(&grid, (0, 1), &["Performer", "Playbacks"]);
+        let albums = history_list(&grid, (1, 1), &["Album", "Playbacks"]);
+        container.pack_start(&grid, true, true, 0);
+
+        HistoryView {
+            period: period,
+            playbacks: playbacks,
+            performers: performers,
+            albums: albums,
+            timeline: timeline,
+        }
+    }
+
+    /// Returns the selected period.
+    pub fn period(&self) -> Period {
+        let id = self.period.get_active_id().map(|id| id.to_string())
+            .unwrap_or_else(|| String::from("all"));
+        Period::parse(&id, now()).unwrap_or_else(|_| Period::all())
+    }
+
+    /// Calls a function when the user selects another period.
+    pub fn connect_period_changed<F: Fn() + 'static>(&self, function: F) {
+        self.period.connect_changed(move |_| function());
+    }
+
+    /// Fills the lists with the history of the selected period.
+    pub fn load(&self, database: &MusicDatabase) -> Result<()> {
+        let period = self.period();
+        self.playbacks.clear();
+        for playback in database.history(&period, Some(HISTORY_LIMIT))? {
+            let completed = if playback.completed { "Yes" } else { "No" };
+            self.playbacks.insert_with_values(None, &[0, 1, 2, 3, 4], &[
+                &format_date(playback.started) as &ToValue,
+                &playback.song.title as &ToValue,
+                &playback.song.performer.name as &ToValue,
+                &format_duration(playback.listened) as &ToValue,
+                &completed as &ToValue,
+            ]);
+        }
+        self.timeline.clear();
+        for day in database.listening_timeline(&period)?.into_iter().rev() {
+            self.timeline.insert_with_values(None, &[0, 1, 2], &[
+                &day.date as &ToValue,
+                &day.playbacks.to_string() as &ToValue,
+                &format_duration(day.listened) as &ToValue,
+            ]);
+        }
+        self.performers.clear();
+        for (performer, count) in database.top_performers(&period, Some(HISTORY_LIMIT))? {
+            self.performers.insert_with_values(None, &[0, 1],
+                &[&performer.name as &ToValue, &count.to_string() as &ToValue]);
+        }
+        self.albums.clear();
+        for (album, count) in database.top_albums(&period, Some(HISTORY_LIMIT))? {
+            self.albums.insert_with_values(None, &[0, 1],
+                &[&album.name as &ToValue, &count.to_string() as &ToValue]);
+        }
+        Ok(())
+    }
+}
+
+/// Creates a list of text columns with the given titles, attaching it to a grid at the given
+/// column and row, and returns its list store.
+fn history_list(grid: &gtk::Grid, (left, top): (i32, i32), titles: &[&str]) -> gtk::ListStore {
+    let types = vec![gtk::Type::String; titles.len()];
+    let list_store = gtk::ListStore::new(&types);
+    let tree_view = gtk::TreeView::new_with_model(&list_store);
+    for (num_column, title) in titles.iter().enumerate() {
+        let cell_renderer = gtk::CellRendererText::new();
+        let view_column = gtk::TreeViewColumn::new();
+        view_column.set_title(title);
+        view_column.set_expand(true);
+        view_column.pack_start(&cell_renderer, true);
+        view_column.add_attribute(&cell_renderer, "text", num_column as i32);
+        tree_view.append_column(&view_column);
+    }
+    let scrolled_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+        None::<&gtk::Adjustment>);
+    scrolled_window.set_shadow_type(gtk::ShadowType::In);
+    scrolled_window.add(&tree_view);
+    grid.attach(&scrolled_window, left, top, 1, 1);
+    list_store
+}
@@ -0,0 +1,123 @@
+use gtk::{self, prelude::*, ResponseType};
+use std::{cell::RefCell, path::PathBuf};
+use music_player_rs::music_manager::{
+    Result,
+    model::{Song, format_date},
+    music_database::MusicDatabase,
+};
+
+/// The songs whose file was not found when the library was mined, and could not be relocated.
+/// The user locates the file of the selected song, or removes the song from the library.
+pub struct MissingView {
+    list_store: gtk::ListStore,
+    tree_view: gtk::TreeView,
+    locate_button: gtk::Button,
+    remove_button: gtk::Button,
+    songs: RefCell<Vec<Song>>,
+}
+
+impl MissingView {
+
+    /// Creates the list of missing songs and its buttons, packing them into a container.
+    pub fn new(container: &gtk::Box) -> MissingView {
+        let titles = ["Title", "Performer", "Album", "Path", "Missing since"];
+        let list_store = gtk::ListStore::new(&[gtk::Type::String; 5]);
+        let tree_view = gtk::TreeView::new_with_model(&list_store);
+        for (num_column, title) in titles.iter().enumerate() {
+            let cell_renderer = gtk::CellRendererText::new();
+            let view_column = gtk::TreeViewColumn::new();
+            view_column.set_title(title);
+            view_column.set_expand(num_column < 4);
+            view_column.pack_start(&cell_renderer, true);
+            view_column.add_attribute(&cell_renderer, "text", num_column as i32);
+            tree_view.append_column(&view_column);
+        }
+        let scrolled_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        scrolled_window.set_shadow_type(gtk::ShadowType::In);
+        scrolled_window.add(&tree_view);
+        container.pack_start(&scrolled_window, true, true, 0);
+
+        let locate_button = gtk::Button::new_with_label("Locate file…");
+        let remove_button = gtk::Button::new_with_label("Remove from library");
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.set_halign(gtk::Align::End);
+        buttons.pack_start(&remove_button, false, false, 0);
+        buttons.pack_start(&locate_button, false, false, 0);
+        container.pack_start(&buttons, false, true, 3);
+
+        locate_button.set_sensitive(false);
+        remove_button.set_sensitive(false);
+        let locate_clone = locate_button.clone();
+        let remove_clone = remove_button.clone();
+        tree_view.get_selection().connect_changed(move |selection| {
+            let selected = selection.get_selected().is_some();
+            locate_clone.set_sensitive(selected);
+            remove_clone.set_sensitive(selected);
+        });
+
+        MissingView {
+            list_store: list_store,
+            tree_view: tree_view,
+            locate_button: locate_button,
+            remove_button: remove_button,
+            songs: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Fills the list with the missing songs.
+    pub fn load(&self, database: &MusicDatabase) -> Result<()> {
+        let missing_songs = database.missing_songs()?;
+        self.list_store.clear();
+        for &(ref song, missing_since) in &missing_songs {
+            self.list_store.insert_with_values(None, &[0, 1, 2, 3, 4], &[
+                &song.title as &ToValue,
+                &song.performer.name as &ToValue,
+                &song.album.name as &ToValue,
+                &song.path.to_string_lossy().into_owned() as &ToValue,
+                &format_date(missing_since) as &ToValue,
+            ]);
+        }
+        *self.songs.borrow_mut() = missing_songs.into_iter().map(|(song, _)| song).collect();
+        Ok(())
+    }
+
+    /// Returns the selected missing song.
+    pub fn selected(&self) -> Option<Song> {
+        let (tree_model, tree_iter) = self.tree_view.get_selection().get_selected()?;
+        let path = tree_model.get_path(&tree_iter)?;
+        let index = *path.get_indices().first()?;
+        self.songs.borrow().get(index as usize).cloned()
+    }
+
+    /// Calls a function when the user wants to locate the file of the selected song.
+    pub fn connect_locate_clicked<F: Fn() + 'static>(&self, function: F) {
+        self.locate_button.connect_clicked(move |_| function());
+    }
+
+    /// Calls a function when the user removes the selected song from the library.
+    pub fn connect_remove_clicked<F: Fn() + 'static>(&self, function: F) {
+        self.remove_button.connect_clicked(move |_| function());
+    }
+}
+
+/// Asks the user the new file of a missing song. Returns the file, or None if the user cancels
+/// the dialog.
+pub fn choose_file<W: IsA<gtk::Window>>(parent: &W, song: &Song) -> Option<PathBuf> {
+    let chooser = gtk::FileChooserDialog::new(Some(&format!("Locate {}", song.title)),
+        Some(parent), gtk::FileChooserAction::Open);
+    chooser.add_button("_Cancel", ResponseType::Cancel.into());
+    chooser.add_button("_Locate", ResponseType::Accept.into());
+    let filter = gtk::FileFilter::new();
+    filter.add_pattern("*.mp3");
+    filter.add_pattern("*.MP3");
+    chooser.set_filter(&filter);
+    let file = if chooser.run() == ResponseType::Accept.into() {
+        chooser.get_filename()
+    }
+    else {
+        None
+    };
+    chooser.destroy();
+    file
+}
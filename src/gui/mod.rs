@@ -0,0 +1,67 @@
+//! Graphical interface of the music player.
+
+/// Application state module.
+pub mod app;
+/// Album grid module.
+pub mod album_grid;
+/// Column browser module.
+pub mod browser;
+/// Songs' list columns module.
+pub mod columns;
+/// Song details module.
+pub mod details;
+/// Duplicates review module.
+pub mod duplicates;
+/// Listening history module.
+pub mod history;
+/// Missing files module.
+pub mod missing;
+/// Smart playlists module.
+pub mod playlists;
+/// Preferences dialog module.
+pub mod preferences;
+/// Search bar module.
+pub mod search;
+/// Library statistics dialog module.
+pub mod statistics;
+
+use gio::prelude::*;
+use gtk;
+use std::{cell::RefCell, rc::Rc};
+use music_player_rs::music_manager::{Error, config::Config};
+use self::app::App;
+
+/// Identifier of the application, used by GTK to identify its instances.
+const APPLICATION_ID: &str = "org.music_player_rs.MusicPlayer";
+
+/// Runs the graphical interface with the given configuration until its window is closed, and
+/// returns the exit code of the program. An error reading the configuration is shown in the
+/// window.
+pub fn run(config: Config, config_error: Option<Error>) -> i32 {
+    let application = match gtk::Application::new(APPLICATION_ID, gio::ApplicationFlags::NON_UNIQUE) {
+        Ok(application) => application,
+        Err(e) => {
+            eprintln!("Error initializing GTK: {}", e);
+            return 1;
+        },
+    };
+    let config_error = RefCell::new(config_error);
+    let app: Rc<RefCell<Option<Rc<App>>>> = Rc::new(RefCell::new(None));
+
+    let app_clone = app.clone();
+    application.connect_activate(move |application| {
+        let mut app = app_clone.borrow_mut();
+        if app.is_none() {
+            *app = Some(App::new(application, config.clone(), config_error.borrow_mut().take()));
+        }
+        if let Some(ref app) = *app {
+            app.present();
+        }
+    });
+    application.connect_shutdown(move |_| {
+        if let Some(ref app) = *app.borrow() {
+            app.shutdown();
+        }
+    });
+    application.run(&[])
+}
@@ -0,0 +1,215 @@
+use gtk::{self, prelude::*};
+use std::{cell::{Cell, RefCell}, rc::Rc};
+use music_player_rs::music_manager::{
+    Error,
+    Result,
+    model::{Song, SmartPlaylist, PlaylistOrder, format_duration},
+};
+
+/// Largest limit of songs that can be chosen for a playlist.
+const MAX_LIMIT: f64 = 10_000.0;
+
+/// The smart playlists: their list, an editor of the selected one, and its songs.
+/// The songs are not kept, they are given by the application every time the playlist is
+/// evaluated, so they follow the library.
+pub struct PlaylistsView {
+    list_store: gtk::ListStore,
+    tree_view: gtk::TreeView,
+    name: gtk::Entry,
+    rules: gtk::Entry,
+    order: gtk::ComboBoxText,
+    limit: gtk::SpinButton,
+    save_button: gtk::Button,
+    delete_button: gtk::Button,
+    songs_store: gtk::ListStore,
+    playlists: RefCell<Vec<SmartPlaylist>>,
+    updating: Rc<Cell<bool>>,
+}
+
+impl PlaylistsView {
+
+    /// Creates the list of playlists, the editor and the songs' list, packing them into a
+    /// container.
+    pub fn new(container: &gtk::Box) -> Rc<PlaylistsView> {
+        let list_store = gtk::ListStore::new(&[gtk::Type::String]);
+        let tree_view = gtk::TreeView::new_with_model(&list_store);
+        tree_view.append_column(&text_column("Playlist", 0, true));
+        let playlists_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        playlists_window.set_shadow_type(gtk::ShadowType::In);
+        playlists_window.add(&tree_view);
+
+        let name = gtk::Entry::new();
+        let rules = gtk::Entry::new();
+        rules.set_placeholder_text(Some("G: rock Y:<1980 R:>=4"));
+        rules.set_tooltip_text(Some("The songs that match this search are in the playlist"));
+        let order = gtk::ComboBoxText::new();
+        for playlist_order in PlaylistOrder::ALL.iter() {
+            order.append(Some(playlist_order.name()), playlist_order.title());
+        }
+        order.set_active(Some(0));
+        let limit = gtk::SpinButton::new_with_range(0.0, MAX_LIMIT, 1.0);
+        limit.set_tooltip_text(Some("Maximum number of songs, 0 for every song"));
+        let save_button = gtk::Button::new_with_label("Save");
+        let delete_button = gtk::Button::new_with_label("Delete");
+        delete_button.set_sensitive(false);
+
+        let editor = gtk::Grid::new();
+        editor.set_row_spacing(3);
+        editor.set_column_spacing(6);
+        for (row, &(label, ref widget)) in [("Name", name.clone().upcast::<gtk::Widget>()),
+            ("Rules", rules.clone().upcast()), ("Order", order.clone().upcast()),
+            ("Limit", limit.clone().upcast())].iter().enumerate() {
+            let label = gtk::Label::new(Some(label));
+            label.set_halign(gtk::Align::End);
+            widget.set_hexpand(true);
+            editor.attach(&label, 0, row as i32, 1, 1);
+            editor.attach(widget, 1, row as i32, 1, 1);
+        }
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        buttons.set_halign(gtk::Align::End);
+        buttons.pack_start(&delete_button, false, false, 0);
+        buttons.pack_start(&save_button, false, false, 0);
+        editor.attach(&buttons, 0, 4, 2, 1);
+
+        let playlists_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        playlists_box.pack_start(&playlists_window, true, true, 0);
+        playlists_box.pack_start(&editor, false, true, 3);
+
+        let songs_store = gtk::ListStore::new(&[gtk::Type::String, gtk::Type::String,
+            gtk::Type::String, gtk::Type::String, gtk::Type::String, gtk::Type::String]);
+        let songs_view = gtk::TreeView::new_with_model(&songs_store);
+        for (num_column, title) in ["Title", "Performer", "Album", "Length", "Plays", "Rating"]
+            .iter().enumerate() {
+            songs_view.append_column(&text_column(title, num_column as i32, num_column < 3));
+        }
+        let songs_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+            None::<&gtk::Adjustment>);
+        songs_window.set_shadow_type(gtk::ShadowType::In);
+        songs_window.add(&songs_view);
+
+        let paned = gtk::Paned::new(gtk::Orientation::Horizontal);
+        paned.pack1(&playlists_box, false, false);
+        paned.pack2(&songs_window, true, false);
+        container.pack_start(&paned, true, true, 0);
+
+        let view = Rc::new(PlaylistsView {
+            list_store: list_store,
+            tree_view: tree_view,
+            name: name,
+            rules: rules,
+            order: order,
+            limit: limit,
+            save_button: save_button,
+            delete_button: delete_button,
+            songs_store: songs_store,
+            playlists: RefCell::new(Vec::new()),
+            updating: Rc::new(Cell::new(false)),
+        });
+        let view_clone = view.clone();
+        view.tree_view.get_selection().connect_changed(move |_| view_clone.edit_selected());
+        view
+    }
+
+    /// Replaces the listed playlists, selecting the one with the given name if it is listed.
+    /// The change of the selection is not notified.
+    pub fn set_playlists(&self, playlists: Vec<SmartPlaylist>, selected: Option<&str>) {
+        self.updating.set(true);
+        self.list_store.clear();
+        for playlist in &playlists {
+            let tree_iter = self.list_store.insert_with_values(None, &[0],
+                &[&playlist.name as &ToValue]);
+            if selected == Some(&playlist.name[..]) {
+                self.tree_view.get_selection().select_iter(&tree_iter);
+            }
+        }
+        *self.playlists.borrow_mut() = playlists;
+        self.updating.set(false);
+        self.edit_selected();
+    }
+
+    /// Returns the selected playlist, as it is stored.
+    pub fn selected(&self) -> Option<SmartPlaylist> {
+        let (tree_model, tree_iter) = self.tree_view.get_selection().get_selected()?;
+        let name = tree_model.get_value(&tree_iter, 0).get::<String>()?;
+        self.playlists.borrow().iter().find(|playlist| playlist.name == name).cloned()
+    }
+
+    /// Returns the playlist written in the editor.
+    pub fn edited(&self) -> Result<SmartPlaylist> {
+        let name = self.name.get_text().map(|name| name.trim().to_owned()).unwrap_or_default();
+        if name.is_empty() {
+            return Err(Error::Parse(String::from("The playlist has no name")));
+        }
+        let order = self.order.get_active_id().map(|id| id.to_string()).unwrap_or_default();
+        let limit = self.limit.get_value_as_int();
+        Ok(SmartPlaylist {
+            id: 0,
+            name: name,
+            rules: self.rules.get_text().map(|rules| rules.to_string()).unwrap_or_default(),
+            order: PlaylistOrder::from_name(&order)?,
+            limit: if limit > 0 { Some(i64::from(limit)) } else { None },
+        })
+    }
+
+    /// Lists the songs of the selected playlist.
+    pub fn set_songs(&self, songs: &[Song]) {
+        self.songs_store.clear();
+        for song in songs {
+            self.songs_store.insert_with_values(None, &[0, 1, 2, 3, 4, 5], &[
+                &song.title as &ToValue,
+                &song.performer.name as &ToValue,
+                &song.album.name as &ToValue,
+                &song.duration.map(format_duration).unwrap_or_default() as &ToValue,
+                &song.play_count.to_string() as &ToValue,
+                &"★".repeat(song.rating as usize) as &ToValue,
+            ]);
+        }
+    }
+
+    /// Calls a function when the user selects another playlist.
+    pub fn connect_selection_changed<F: Fn() + 'static>(&self, function: F) {
+        let updating = self.updating.clone();
+        self.tree_view.get_selection().connect_changed(move |_| {
+            if !updating.get() {
+                function();
+            }
+        });
+    }
+
+    /// Calls a function when the user saves the playlist of the editor.
+    pub fn connect_save_clicked<F: Fn() + 'static>(&self, function: F) {
+        self.save_button.connect_clicked(move |_| function());
+    }
+
+    /// Calls a function when the user deletes the selected playlist.
+    pub fn connect_delete_clicked<F: Fn() + 'static>(&self, function: F) {
+        self.delete_button.connect_clicked(move |_| function());
+    }
+
+    /// Fills the editor with the selected playlist, if there is one, so it can be changed and
+    /// saved again. Without a selection the editor is kept, to create a new playlist.
+    fn edit_selected(&self) {
+        let playlist = self.selected();
+        self.delete_button.set_sensitive(playlist.is_some());
+        let playlist = match playlist {
+            Some(playlist) => playlist,
+            None => return,
+        };
+        self.name.set_text(&playlist.name);
+        self.rules.set_text(&playlist.rules);
+        self.order.set_active_id(Some(playlist.order.name()));
+        self.limit.set_value(playlist.limit.unwrap_or(0) as f64);
+    }
+}
+
+/// Creates a column of a list that shows the text of a column of its model.
+fn text_column(title: &str, column: i32, expand: bool) -> gtk::TreeViewColumn {
+    let cell_renderer = gtk::CellRendererText::new();
+    let view_column = gtk::TreeViewColumn::new();
+    view_column.set_title(title);
+    view_column.set_expand(expand);
+    view_column.pack_start(&cell_renderer, true);
+    view_column.add_attribute(&cell_renderer, "text", column);
+    view_column
+}
@@ -0,0 +1,173 @@
+use gtk::prelude::*;
+use gtk::{self, ResponseType};
+use std::path::{Path, PathBuf};
+use music_player_rs::music_manager::{Result, config::Config};
+
+/// Verbosity levels of the log, by verbosity.
+const VERBOSITY_LEVELS: [&str; 4] = ["Off", "Info", "Warn", "Max"];
+
+/// Shows the preferences dialog, where the database, the log and the library (its roots, the
+/// exclusion patterns and the symbolic links policy) are edited. The configuration is saved to
+/// the given configuration file when the user accepts the dialog, and in that case true is
+/// returned. Values given by environment variables or command-line flags are not saved.
+pub fn run<W: IsA<gtk::Window>>(parent: &W, file: Option<&Path>) -> Result<bool> {
+    let mut config = Config::read(file)?;
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some("Preferences"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("_Cancel", ResponseType::Cancel.into()), ("_Save", ResponseType::Accept.into())]);
+    dialog.set_default_size(480, 480);
+
+    let database_entry = gtk::Entry::new();
+    database_entry.set_text(&config.database.path.to_string_lossy());
+    database_entry.set_hexpand(true);
+    let log_entry = gtk::Entry::new();
+    log_entry.set_text(&config.log.file.to_string_lossy());
+    let verbosity_combo = gtk::ComboBoxText::new();
+    for level in VERBOSITY_LEVELS.iter() {
+        verbosity_combo.append_text(level);
+    }
+    verbosity_combo.set_active(Some(config.log.verbosity.min(3) as u32));
+    let general_grid = gtk::Grid::new();
+    general_grid.set_row_spacing(6);
+    general_grid.set_column_spacing(6);
+    general_grid.attach(&heading("Database file"), 0, 0, 1, 1);
+    general_grid.attach(&database_entry, 1, 0, 1, 1);
+    general_grid.attach(&heading("Log file"), 0, 1, 1, 1);
+    general_grid.attach(&log_entry, 1, 1, 1, 1);
+    general_grid.attach(&heading("Log verbosity"), 0, 2, 1, 1);
+    general_grid.attach(&verbosity_combo, 1, 2, 1, 1);
+
+    let roots = gtk::ListStore::new(&[gtk::Type::String]);
+    for root in &config.library.roots {
+        append_root(&roots, root);
+    }
+    let roots_view = gtk::TreeView::new_with_model(&roots);
+    roots_view.set_headers_visible(false);
+    let cell_renderer = gtk::CellRendererText::new();
+    let view_column = gtk::TreeViewColumn::new();
+    view_column.pack_start(&cell_renderer, true);
+    view_column.add_attribute(&cell_renderer, "text", 0);
+    roots_view.append_column(&view_column);
+    let roots_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>, None::<&gtk::Adjustment>);
+    roots_window.add(&roots_view);
+
+    let add_button = gtk::Button::new_with_mnemonic("_Add folder");
+    let remove_button = gtk::Button::new_with_mnemonic("_Remove folder");
+    let buttons_box = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    buttons_box.pack_start(&add_button, false, false, 0);
+    buttons_box.pack_start(&remove_button, false, false, 0);
+
+    let roots_clone = roots.clone();
+    let dialog_clone = dialog.clone();
+    add_button.connect_clicked(move |_| {
+        if let Some(folder) = choose_folder(&dialog_clone) {
+            append_root(&roots_clone, &folder);
+        }
+    });
+    let roots_clone = roots.clone();
+    let roots_view_clone = roots_view.clone();
+    remove_button.connect_clicked(move |_| {
+        if let Some((_, tree_iter)) = roots_view_clone.get_selection().get_selected() {
+            roots_clone.remove(&tree_iter);
+        }
+    });
+
+    let exclude_buffer = gtk::TextBuffer::new(None::<&gtk::TextTagTable>);
+    exclude_buffer.set_text(&config.library.exclude.join("\n"));
+    let exclude_view = gtk::TextView::new_with_buffer(&exclude_buffer);
+    let follow_symlinks = gtk::CheckButton::new_with_label("Follow symbolic links");
+    follow_symlinks.set_active(config.library.follow_symlinks);
+
+    let content_area = dialog.get_content_area();
+    content_area.set_spacing(6);
+    content_area.set_border_width(6);
+    content_area.pack_start(&general_grid, false, false, 0);
+    content_area.pack_start(&heading("Library folders"), false, false, 0);
+    content_area.pack_start(&roots_window, true, true, 0);
+    content_area.pack_start(&buttons_box, false, false, 0);
+    content_area.pack_start(&heading("Excluded paths, one pattern per line (e.g. */Podcasts/*)"),
+        false, false, 0);
+    content_area.pack_start(&exclude_view, true, true, 0);
+    content_area.pack_start(&follow_symlinks, false, false, 0);
+    dialog.show_all();
+
+    let result = if dialog.run() == ResponseType::Accept.into() {
+        if let Some(path) = database_entry.get_text() {
+            config.database.path = PathBuf::from(path.as_str());
+        }
+        if let Some(file) = log_entry.get_text() {
+            config.log.file = PathBuf::from(file.as_str());
+        }
+        if let Some(verbosity) = verbosity_combo.get_active() {
+            config.log.verbosity = verbosity as u64;
+        }
+        config.library.roots = roots_from(&roots);
+        config.library.exclude = exclude_from(&exclude_buffer);
+        config.library.follow_symlinks = follow_symlinks.get_active();
+        config.library.patterns().and_then(|_| config.save()).map(|_| true)
+    }
+    else {
+        Ok(false)
+    };
+    dialog.destroy();
+    result
+}
+
+/// Asks the user for a folder to add to the library.
+fn choose_folder(parent: &gtk::Dialog) -> Option<PathBuf> {
+    let chooser = gtk::FileChooserDialog::new(Some("Add library folder"), Some(parent),
+        gtk::FileChooserAction::SelectFolder);
+    chooser.add_button("_Cancel", ResponseType::Cancel.into());
+    chooser.add_button("_Add", ResponseType::Accept.into());
+    let folder = if chooser.run() == ResponseType::Accept.into() {
+        chooser.get_filename()
+    }
+    else {
+        None
+    };
+    chooser.destroy();
+    folder
+}
+
+/// Returns a label aligned to the left, used as the heading of a section of the dialog.
+fn heading(text: &str) -> gtk::Label {
+    let label = gtk::Label::new(Some(text));
+    label.set_xalign(0.0);
+    label
+}
+
+/// Appends a library root to the list of roots.
+fn append_root(roots: &gtk::ListStore, root: &PathBuf) {
+    let root = root.to_string_lossy().into_owned();
+    roots.insert_with_values(None, &[0], &[&root as &ToValue]);
+}
+
+/// Returns the library roots in the list of roots.
+fn roots_from(roots: &gtk::ListStore) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(tree_iter) = roots.get_iter_first() {
+        loop {
+            if let Some(root) = roots.get_value(&tree_iter, 0).get::<String>() {
+                paths.push(PathBuf::from(root));
+            }
+            if !roots.iter_next(&tree_iter) {
+                break;
+            }
+        }
+    }
+    paths
+}
+
+/// Returns the non-empty lines of the exclusion patterns text.
+fn exclude_from(buffer: &gtk::TextBuffer) -> Vec<String> {
+    buffer.get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), false)
+        .map(|text| text.to_string())
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
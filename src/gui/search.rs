@@ -0,0 +1,230 @@
+use glib;
+use gtk::{self, prelude::*, ResponseType};
+use std::{cell::RefCell, rc::Rc};
+use music_player_rs::music_manager::model::SavedSearch;
+
+/// Milliseconds the user has to stop typing before the search runs and the completions are
+/// looked up.
+const SEARCH_DELAY: u32 = 300;
+
+/// The search bar: an entry that searches as the user types, completing the values after a
+/// prefix and the recent and saved searches, and a menu to run, save and delete saved searches.
+/// Saving and deleting are the "app.save-search" and "app.delete-search" actions, the latter
+/// with the id of the saved search as parameter.
+pub struct SearchBar {
+    entry: gtk::SearchEntry,
+    completion: gtk::EntryCompletion,
+    completion_store: gtk::ListStore,
+    searches: RefCell<Vec<(String, String)>>,
+    menu_button: gtk::MenuButton,
+    pending: RefCell<Option<glib::SourceId>>,
+    pending_completions: RefCell<Option<glib::SourceId>>,
+}
+
+impl SearchBar {
+
+    /// Gets the entry and the saved searches' button from the builder of the window, and adds
+    /// the completion to the entry.
+    pub fn from_builder(builder: &gtk::Builder) -> Rc<SearchBar> {
+        let entry: gtk::SearchEntry = builder.get_object("SearchBar").unwrap();
+        let completion_store = gtk::ListStore::new(&[gtk::Type::String, gtk::Type::String]);
+        let completion = gtk::EntryCompletion::new();
+        completion.set_model(Some(&completion_store));
+        completion.set_text_column(0);
+        completion.set_match_func(|completion, key, tree_iter| {
+            let model = match completion.get_model() {
+                Some(model) => model,
+                None => return false,
+            };
+            (0..2).any(|column| {
+                model.get_value(tree_iter, column).get::<String>()
+                    .map_or(false, |text| text.to_lowercase().starts_with(key))
+            })
+        });
+        let label_renderer = gtk::CellRendererText::new();
+        label_renderer.set_property_xalign(1.0);
+        label_renderer.set_sensitive(false);
+        completion.pack_start(&label_renderer, false);
+        completion.add_attribute(&label_renderer, "text", 1);
+        entry.set_completion(Some(&completion));
+
+        Rc::new(SearchBar {
+            entry: entry,
+            completion: completion,
+            completion_store: completion_store,
+            searches: RefCell::new(Vec::new()),
+            menu_button: builder.get_object("SavedSearchesButton").unwrap(),
+            pending: RefCell::new(None),
+            pending_completions: RefCell::new(None),
+        })
+    }
+
+    /// Returns the query written in the entry.
+    pub fn text(&self) -> String {
+        self.entry.get_text().map(|text| text.to_string()).unwrap_or_default()
+    }
+
+    /// Moves the keyboard focus to the entry.
+    pub fn grab_focus(&self) {
+        self.entry.grab_focus();
+    }
+
+    /// Calls a function with the query when the user stops typing for `SEARCH_DELAY`
+    /// milliseconds, and right away when the user presses Enter. The second argument tells
+    /// whether the user pressed Enter.
+    pub fn connect_search<F: Fn(&str, bool) + 'static>(bar: &Rc<SearchBar>, function: F) {
+        let function = Rc::new(function);
+        let bar_clone = bar.clone();
+        let function_clone = function.clone();
+        bar.entry.connect_changed(move |_| {
+            bar_clone.cancel_pending();
+            let bar = bar_clone.clone();
+            let function = function_clone.clone();
+            let source = gtk::timeout_add(SEARCH_DELAY, move || {
+                bar.pending.borrow_mut().take();
+                function(&bar.text(), false);
+                glib::Continue(false)
+            });
+            *bar_clone.pending.borrow_mut() = Some(source);
+        });
+        let bar_clone = bar.clone();
+        bar.entry.connect_activate(move |_| {
+            bar_clone.cancel_pending();
+            function(&bar_clone.text(), true);
+        });
+    }
+
+    /// Calls a function with the query when the user stops typing for `SEARCH_DELAY`
+    /// milliseconds, offering the queries it returns as completions before the recent and saved
+    /// searches.
+    pub fn connect_completions<F: Fn(&str) -> Vec<String> + 'static>(bar: &Rc<SearchBar>,
+        function: F) {
+        let function = Rc::new(function);
+        let bar_clone = bar.clone();
+        bar.entry.connect_changed(move |_| {
+            cancel(&bar_clone.pending_completions);
+            let bar = bar_clone.clone();
+            let function = function.clone();
+            let source = gtk::timeout_add(SEARCH_DELAY, move || {
+                bar.pending_completions.borrow_mut().take();
+                let completions = function(&bar.text());
+                bar.fill_completions(&completions);
+                if !completions.is_empty() {
+                    bar.completion.complete();
+                }
+                glib::Continue(false)
+            });
+            *bar_clone.pending_completions.borrow_mut() = Some(source);
+        });
+    }
+
+    /// Offers the recent and saved searches as completions, and lists the saved searches in
+    /// the menu.
+    pub fn set_searches(&self, recent: &[String], saved: &[SavedSearch]) {
+        {
+            let mut searches = self.searches.borrow_mut();
+            searches.clear();
+            for search in saved {
+                searches.push((search.query.clone(), search.name.clone()));
+            }
+            for query in recent {
+                if saved.iter().all(|search| search.query != *query) {
+                    searches.push((query.clone(), String::from("Recent")));
+                }
+            }
+        }
+        self.fill_completions(&[]);
+
+        let menu = gtk::Menu::new();
+        for search in saved {
+            let item = gtk::MenuItem::new_with_label(&search.name);
+            item.set_tooltip_text(Some(&search.query));
+            let entry = self.entry.clone();
+            let query = search.query.clone();
+            item.connect_activate(move |_| {
+                entry.set_text(&query);
+                entry.activate();
+            });
+            menu.append(&item);
+        }
+        if !saved.is_empty() {
+            menu.append(&gtk::SeparatorMenuItem::new());
+        }
+        let save_item = gtk::MenuItem::new_with_label("Save search…");
+        save_item.set_action_name(Some("app.save-search"));
+        menu.append(&save_item);
+        if !saved.is_empty() {
+            let delete_menu = gtk::Menu::new();
+            for search in saved {
+                let item = gtk::MenuItem::new_with_label(&search.name);
+                item.set_detailed_action_name(&format!("app.delete-search(int64 {})", search.id));
+                delete_menu.append(&item);
+            }
+            let delete_item = gtk::MenuItem::new_with_label("Delete saved search");
+            delete_item.set_submenu(Some(&delete_menu));
+            menu.append(&delete_item);
+        }
+        menu.show_all();
+        self.menu_button.set_popup(Some(&menu));
+    }
+
+    /// Replaces the completions with the given queries followed by the recent and saved
+    /// searches.
+    fn fill_completions(&self, completions: &[String]) {
+        self.completion_store.clear();
+        for query in completions {
+            self.completion_store.insert_with_values(None, &[0, 1],
+                &[query as &ToValue, &"" as &ToValue]);
+        }
+        for &(ref query, ref label) in self.searches.borrow().iter() {
+            self.completion_store.insert_with_values(None, &[0, 1],
+                &[query as &ToValue, label as &ToValue]);
+        }
+    }
+
+    /// Cancels the search and the completions waiting for the user to stop typing, if there
+    /// are.
+    fn cancel_pending(&self) {
+        cancel(&self.pending);
+        cancel(&self.pending_completions);
+    }
+}
+
+/// Removes a source waiting for the user to stop typing, if there is one.
+fn cancel(pending: &RefCell<Option<glib::SourceId>>) {
+    if let Some(source) = pending.borrow_mut().take() {
+        glib::source_remove(source);
+    }
+}
+
+/// Asks the user the name to save a query with. Returns the name, or None if the user cancels
+/// the dialog.
+pub fn ask_name<W: IsA<gtk::Window>>(parent: &W, query: &str) -> Option<String> {
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some("Save search"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("_Cancel", ResponseType::Cancel.into()), ("_Save", ResponseType::Accept.into())]);
+    dialog.set_default_response(ResponseType::Accept.into());
+    let name_entry = gtk::Entry::new();
+    name_entry.set_activates_default(true);
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(6);
+    grid.set_border_width(6);
+    grid.attach(&gtk::Label::new(Some("Search")), 0, 0, 1, 1);
+    grid.attach(&gtk::Label::new(Some(query)), 1, 0, 1, 1);
+    grid.attach(&gtk::Label::new(Some("Name")), 0, 1, 1, 1);
+    grid.attach(&name_entry, 1, 1, 1, 1);
+    dialog.get_content_area().pack_start(&grid, true, true, 0);
+    dialog.show_all();
+
+    let response = dialog.run();
+    let name = name_entry.get_text().map(|name| name.to_string()).unwrap_or_default();
+    dialog.destroy();
+    if response == ResponseType::Accept.into() {
+        Some(name)
+    } else {
+        None
+    }
+}
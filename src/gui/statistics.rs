@@ -0,0 +1,95 @@
+use gtk::prelude::*;
+use gtk::{self, ResponseType};
+use music_player_rs::music_manager::{
+    model::{LibraryStatistics, format_long_duration, format_size},
+};
+
+/// Shows the statistics of the library in a dialog: its counts, duration and size, the songs
+/// with missing tags or art, the hidden and missing songs, the songs per format, and a
+/// histogram of the songs per year.
+pub fn run<W: IsA<gtk::Window>>(parent: &W, statistics: &LibraryStatistics) {
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some("Library statistics"),
+        Some(parent),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("_Close", ResponseType::Close.into())]);
+    dialog.set_default_size(420, 520);
+
+    let summary = grid();
+    let rows = [
+        ("Songs", statistics.songs.to_string()),
+        ("Albums", statistics.albums.to_string()),
+        ("Performers", statistics.performers.to_string()),
+        ("Genres", statistics.genres.to_string()),
+        ("Duration", format_long_duration(statistics.duration)),
+        ("Size", format_size(statistics.size)),
+        ("Songs with missing tags", statistics.missing_tags.to_string()),
+        ("Songs without cover art", statistics.missing_art.to_string()),
+        ("Hidden songs", statistics.hidden.to_string()),
+        ("Missing songs", statistics.missing.to_string()),
+    ];
+    for (row, &(name, ref value)) in rows.iter().enumerate() {
+        summary.attach(&heading(name), 0, row as i32, 1, 1);
+        summary.attach(&value_label(value), 1, row as i32, 1, 1);
+    }
+    let formats = grid();
+    for (row, &(ref format, songs)) in statistics.formats.iter().enumerate() {
+        let format = if format.is_empty() { "No extension" } else { format.as_str() };
+        formats.attach(&heading(format), 0, row as i32, 1, 1);
+        formats.attach(&value_label(&songs.to_string()), 1, row as i32, 1, 1);
+    }
+
+    let years = grid();
+    let most_songs = statistics.years.iter().map(|&(_, songs)| songs).max().unwrap_or(0);
+    for (row, &(year, songs)) in statistics.years.iter().enumerate() {
+        let bar = gtk::LevelBar::new_for_interval(0.0, most_songs as f64);
+        bar.set_value(songs as f64);
+        bar.set_hexpand(true);
+        bar.set_valign(gtk::Align::Center);
+        bar.set_tooltip_text(Some(&format!("{} songs", songs)));
+        years.attach(&heading(&year.to_string()), 0, row as i32, 1, 1);
+        years.attach(&bar, 1, row as i32, 1, 1);
+        years.attach(&value_label(&songs.to_string()), 2, row as i32, 1, 1);
+    }
+    let years_window = gtk::ScrolledWindow::new(None::<&gtk::Adjustment>,
+        None::<&gtk::Adjustment>);
+    years_window.set_vexpand(true);
+    years_window.add(&years);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    content.set_border_width(6);
+    for &(title, ref widget) in [("Library", summary.upcast::<gtk::Widget>()),
+        ("Formats", formats.upcast()), ("Songs per year", years_window.upcast())].iter() {
+        let frame = gtk::Frame::new(Some(title));
+        frame.add(widget);
+        content.pack_start(&frame, title == "Songs per year", true, 0);
+    }
+    dialog.get_content_area().pack_start(&content, true, true, 0);
+    dialog.show_all();
+    dialog.run();
+    dialog.destroy();
+}
+
+/// Creates a grid of names and values.
+fn grid() -> gtk::Grid {
+    let grid = gtk::Grid::new();
+    grid.set_row_spacing(3);
+    grid.set_column_spacing(12);
+    grid.set_border_width(6);
+    grid
+}
+
+/// Creates the label of a name, aligned to the left.
+fn heading(text: &str) -> gtk::Label {
+    let label = gtk::Label::new(Some(text));
+    label.set_halign(gtk::Align::Start);
+    label
+}
+
+/// Creates the label of a value, aligned to the right.
+fn value_label(text: &str) -> gtk::Label {
+    let label = gtk::Label::new(Some(text));
+    label.set_halign(gtk::Align::End);
+    label.set_hexpand(true);
+    label
+}
@@ -1,9 +1,16 @@
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate serde_derive;
 
 extern crate dirs;
 extern crate id3;
 extern crate sqlite;
+extern crate serde;
+extern crate toml;
+extern crate glob;
+#[cfg(test)]
+extern crate serde_json;
 
 pub mod music_manager;
 
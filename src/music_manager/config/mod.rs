@@ -0,0 +1,253 @@
+use std::{env, fs, path::{Path, PathBuf}};
+use dirs;
+use toml;
+use super::miner::library::LibrarySettings;
+use super::music_database::MusicDatabase;
+use super::model::SongField;
+use super::error::{Error, Result};
+
+/// Environment variable with the path of the configuration file.
+pub const CONFIG_VAR: &str = "MUSIC_PLAYER_CONFIG";
+/// Environment variable with the path of the database.
+pub const DATABASE_VAR: &str = "MUSIC_PLAYER_DATABASE";
+/// Environment variable with the path of the log file.
+pub const LOG_FILE_VAR: &str = "MUSIC_PLAYER_LOG";
+/// Environment variable with the verbosity of the log.
+pub const VERBOSITY_VAR: &str = "MUSIC_PLAYER_VERBOSITY";
+/// Environment variable with the library roots, separated like the PATH variable.
+pub const LIBRARY_VAR: &str = "MUSIC_PLAYER_LIBRARY";
+
+/// The configuration of the music player, stored as a TOML file in the configuration directory.
+/// Missing values take their default value, so an empty file is a valid configuration.
+/// The configuration remembers the file it was read from, where it is saved.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub database: DatabaseSettings,
+    pub log: LogSettings,
+    pub library: LibrarySettings,
+    pub view: ViewSettings,
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
+}
+
+/// The settings of the music database.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    pub path: PathBuf,
+}
+
+impl Default for DatabaseSettings {
+
+    fn default() -> DatabaseSettings {
+        DatabaseSettings {
+            path: PathBuf::from("./music_player_rs.db"),
+        }
+    }
+}
+
+/// The settings of the log: the file where it is written and its verbosity, from 0 (off) to 3
+/// (everything).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    pub file: PathBuf,
+    pub verbosity: u64,
+}
+
+impl Default for LogSettings {
+
+    fn default() -> LogSettings {
+        LogSettings {
+            file: PathBuf::from("music_player.log"),
+            verbosity: 0,
+        }
+    }
+}
+
+/// The layout of the songs' list in the GUI: the field the songs are sorted by and the visible
+/// columns, in order, with their widths in pixels (0 to size them automatically).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    pub sort: Option<SongField>,
+    pub sort_descending: bool,
+    pub columns: Vec<ColumnSettings>,
+}
+
+impl Default for ViewSettings {
+
+    fn default() -> ViewSettings {
+        ViewSettings {
+            sort: None,
+            sort_descending: false,
+            columns: [SongField::Title, SongField::Artist, SongField::Album, SongField::Genre]
+                .iter()
+                .map(|field| ColumnSettings { field: *field, width: 0 })
+                .collect(),
+        }
+    }
+}
+
+/// A visible column of the songs' list.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColumnSettings {
+    pub field: SongField,
+    #[serde(default)]
+    pub width: i32,
+}
+
+impl Config {
+
+    /// Returns the path of the default configuration file: the one in the
+    /// `MUSIC_PLAYER_CONFIG` environment variable, or config.toml inside the configuration
+    /// directory.
+    pub fn path() -> Option<PathBuf> {
+        match env::var_os(CONFIG_VAR) {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::config_dir().map(|mut path| {
+                path.push("music_player_rs");
+                path.push("config.toml");
+                path
+            }),
+        }
+    }
+
+    /// Reads the given configuration file, or the default one if there is none, overridden by
+    /// the environment variables. Only the music player's variables are read, and they must be
+    /// valid Unicode.
+    pub fn load(file: Option<&Path>) -> Result<Config> {
+        let mut config = Config::read(file)?;
+        let mut vars = Vec::new();
+        for name in &[DATABASE_VAR, LOG_FILE_VAR, VERBOSITY_VAR, LIBRARY_VAR] {
+            if let Some(value) = env::var_os(name) {
+                let value = value.into_string()
+                    .map_err(|value| Error::Parse(format!("Invalid {}: {:?}", name, value)))?;
+                vars.push((name.to_string(), value));
+            }
+        }
+        config.apply_vars(vars)?;
+        Ok(config)
+    }
+
+    /// Reads the given configuration file, or the default one if there is none, without the
+    /// environment variables. If the file does not exist, the default configuration is
+    /// returned; it is only written to that file when it is saved.
+    /// This is the configuration that should be changed and saved back.
+    pub fn read(file: Option<&Path>) -> Result<Config> {
+        let path = file.map(Path::to_path_buf).or_else(Config::path);
+        let mut config = match path {
+            Some(ref path) if path.exists() => Config::from_file(path)?,
+            _ => Config::default(),
+        };
+        config.file = path;
+        Ok(config)
+    }
+
+    /// Reads the configuration from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Config> {
+        let content = fs::read_to_string(path)?;
+        let config: Config = toml::from_str(&content)
+            .map_err(|e| Error::Parse(format!("Invalid configuration {:?}: {}", path, e)))?;
+        config.library.patterns()?;
+        Ok(config)
+    }
+
+    /// Writes the configuration to the file it was read from, or to the default one.
+    pub fn save(&self) -> Result<()> {
+        match self.file.clone().or_else(Config::path) {
+            Some(path) => self.to_file(&path),
+            None => Err(Error::NotFound(String::from("configuration directory"))),
+        }
+    }
+
+    /// Writes the configuration to a TOML file, creating its directory if needed.
+    pub fn to_file(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self)
+            .map_err(|e| Error::Parse(format!("Invalid configuration: {}", e)))?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Overrides the configuration with the music player's environment variables among the
+    /// given ones.
+    pub fn apply_vars<I: IntoIterator<Item = (String, String)>>(&mut self, vars: I) -> Result<()> {
+        for (name, value) in vars {
+            match name.as_str() {
+                DATABASE_VAR => self.database.path = PathBuf::from(value),
+                LOG_FILE_VAR => self.log.file = PathBuf::from(value),
+                VERBOSITY_VAR => {
+                    self.log.verbosity = value.parse()
+                        .map_err(|_| Error::Parse(format!("Invalid {}: {:?}", name, value)))?;
+                },
+                LIBRARY_VAR => self.library.roots = env::split_paths(&value).collect(),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a connection to the configured database.
+    pub fn database(&self) -> Result<MusicDatabase> {
+        let mut database = MusicDatabase::new();
+        database.with_database(&self.database.path.to_string_lossy());
+        database.connect()?;
+        Ok(database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_values_are_default() {
+        let config: Config = toml::from_str("[log]\nverbosity = 2\n").unwrap();
+        assert_eq!(config.log.verbosity, 2);
+        assert_eq!(config.log.file, LogSettings::default().file);
+        assert_eq!(config.database, DatabaseSettings::default());
+        assert_eq!(config.library, LibrarySettings::default());
+    }
+
+    #[test]
+    fn variables_override_file() {
+        let mut config: Config = toml::from_str("[database]\npath = \"file.db\"\n").unwrap();
+        config.apply_vars(vec![
+            (String::from(DATABASE_VAR), String::from("variable.db")),
+            (String::from(VERBOSITY_VAR), String::from("3")),
+            (String::from("HOME"), String::from("/home")),
+        ]).unwrap();
+        assert_eq!(config.database.path, PathBuf::from("variable.db"));
+        assert_eq!(config.log.verbosity, 3);
+        assert!(config.apply_vars(vec![(String::from(VERBOSITY_VAR), String::from("loud"))])
+            .is_err());
+    }
+
+    #[test]
+    fn configuration_is_saved_to_its_file() {
+        use std::process;
+        let file = env::temp_dir().join(format!("music_player_rs_config_{}.toml", process::id()));
+        let mut config = Config::read(Some(&file)).unwrap();
+        assert_eq!(config.file, Some(file.clone()));
+        config.log.verbosity = 2;
+        config.save().unwrap();
+        let read = Config::read(Some(&file));
+        fs::remove_file(&file).unwrap();
+        assert_eq!(read.unwrap(), config);
+    }
+
+    #[test]
+    fn view_is_saved_and_read() {
+        let mut config = Config::default();
+        config.view.sort = Some(SongField::Year);
+        config.view.sort_descending = true;
+        config.view.columns[1].width = 120;
+        config.view.columns.push(ColumnSettings { field: SongField::Duration, width: 0 });
+        let content = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&content).unwrap(), config);
+    }
+}
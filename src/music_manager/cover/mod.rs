@@ -0,0 +1,68 @@
+use std::{fs, path::{Path, PathBuf}};
+use id3::Tag;
+use super::model::AlbumEntry;
+
+/// Names (without extension) of the image files that are taken as the cover of the album in
+/// their directory, in order of preference.
+pub const COVER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+
+/// Extensions of the image files that may be covers.
+pub const COVER_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Returns the image data of the cover of an album: an image file in the album's directory or,
+/// if there is none, the picture embedded in the tag of one of its songs.
+pub fn album_cover(entry: &AlbumEntry) -> Option<Vec<u8>> {
+    let file = entry.album.path.as_ref().and_then(|directory| cover_file(directory));
+    match file {
+        Some(file) => fs::read(file).ok(),
+        None => entry.song_path.as_ref().and_then(|path| embedded_cover(path)),
+    }
+}
+
+/// Returns the image file of a directory that is its cover, comparing the names without case.
+pub fn cover_file(directory: &Path) -> Option<PathBuf> {
+    let mut images: Vec<(usize, PathBuf)> = fs::read_dir(directory).ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let is_image = path.extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .map_or(false, |extension| COVER_EXTENSIONS.contains(&extension.as_str()));
+            if !is_image {
+                return None;
+            }
+            let name = path.file_stem()?.to_string_lossy().to_lowercase();
+            let preference = COVER_NAMES.iter().position(|cover_name| *cover_name == name)?;
+            Some((preference, path))
+        })
+        .collect();
+    images.sort();
+    images.into_iter().next().map(|(_, path)| path)
+}
+
+/// Returns the data of the first picture embedded in the tag of a music file.
+pub fn embedded_cover(path: &Path) -> Option<Vec<u8>> {
+    let tag = Tag::read_from_path(path).ok()?;
+    let picture = tag.pictures().next()?;
+    Some(picture.data.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs::File, process};
+
+    #[test]
+    fn cover_files_are_chosen_by_name() {
+        let directory = env::temp_dir().join(format!("music_player_rs_cover_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        assert_eq!(cover_file(&directory), None);
+        File::create(directory.join("booklet.jpg")).unwrap();
+        File::create(directory.join("Folder.JPG")).unwrap();
+        assert_eq!(cover_file(&directory), Some(directory.join("Folder.JPG")));
+        File::create(directory.join("cover.png")).unwrap();
+        assert_eq!(cover_file(&directory), Some(directory.join("cover.png")));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+use std::{error, fmt, io, result};
+use id3;
+use sqlite;
+use super::model::MAX_RATING;
+
+/// Result type returned by the music manager's public APIs.
+pub type Result<T> = result::Result<T, Error>;
+
+/// Errors that may occur while mining, storing or searching music.
+#[derive(Debug)]
+pub enum Error {
+    /// The SQLite database returned an error.
+    Database(sqlite::Error),
+    /// The ID3 tag of a music file could not be read.
+    Tag(id3::Error),
+    /// A file or directory could not be read.
+    Io(io::Error),
+    /// A value could not be parsed (e.g. an unknown table name).
+    Parse(String),
+    /// A rating is greater than `MAX_RATING`.
+    InvalidRating(u8),
+    /// A stored item (e.g. a song, by its id) was not found.
+    NotFound(String),
+    /// An operation needed a database connection, but there is none.
+    NotConnected,
+    /// The operation was cancelled (e.g. the mining, through its handle).
+    Cancelled,
+}
+
+impl fmt::Display for Error {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Database(e) => write!(f, "Database error: {}", e),
+            Error::Tag(e) => write!(f, "Tag error: {}", e),
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::Parse(message) => write!(f, "Parse error: {}", message),
+            Error::InvalidRating(rating) =>
+                write!(f, "Invalid rating {}, it must be from 0 to {}", rating, MAX_RATING),
+            Error::NotFound(item) => write!(f, "Not found: {}", item),
+            Error::NotConnected => write!(f, "Not connected to database"),
+            Error::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl error::Error for Error {
+
+    fn description(&self) -> &str {
+        match self {
+            Error::Database(_) => "database error",
+            Error::Tag(_) => "tag error",
+            Error::Io(_) => "io error",
+            Error::Parse(_) => "parse error",
+            Error::InvalidRating(_) => "invalid rating",
+            Error::NotFound(_) => "not found",
+            Error::NotConnected => "not connected to database",
+            Error::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl From<sqlite::Error> for Error {
+
+    fn from(error: sqlite::Error) -> Error {
+        Error::Database(error)
+    }
+}
+
+impl From<id3::Error> for Error {
+
+    fn from(error: id3::Error) -> Error {
+        Error::Tag(error)
+    }
+}
+
+impl From<io::Error> for Error {
+
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
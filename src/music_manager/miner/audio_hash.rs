@@ -0,0 +1,110 @@
+use std::{fs, io::{self, BufRead, BufReader, Read, Seek, SeekFrom}, path};
+
+/// Size of the header (and of the footer) of an ID3v2 tag.
+const ID3V2_HEADER_SIZE: u64 = 10;
+
+/// Size of an ID3v1 tag, stored at the end of the file.
+const ID3V1_SIZE: u64 = 128;
+
+/// Initial value of the FNV-1a hash.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Returns the hash of the audio of a music file, as hexadecimal text. The ID3 tags are not
+/// part of the audio, so two copies of a recording with different tags have the same hash.
+/// The file is read in blocks, so it is never loaded whole in memory.
+pub fn audio_hash(path: &path::Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let (start, end) = audio_range(&mut file)?;
+    file.seek(SeekFrom::Start(start))?;
+    Ok(format!("{:016x}", hash_reader(file.take(end - start))?))
+}
+
+/// Returns the range of bytes of a music file that hold its audio: the bytes after its ID3v2
+/// tag and the zero padding that may follow it, at the beginning, and before its ID3v1 tag, at
+/// the end. Audio frames start with a sync byte, so they never start with a zero.
+pub fn audio_range<R: Read + Seek>(reader: &mut R) -> io::Result<(u64, u64)> {
+    let length = reader.seek(SeekFrom::End(0))?;
+    let mut start = 0;
+    if length >= ID3V2_HEADER_SIZE {
+        let mut header = [0u8; ID3V2_HEADER_SIZE as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut header)?;
+        if &header[..3] == b"ID3" {
+            let size = header[6..10].iter()
+                .fold(0, |size, byte| (size << 7) | (u64::from(*byte) & 0x7f));
+            let has_footer = header[5] & 0x10 != 0;
+            start = ID3V2_HEADER_SIZE + size + if has_footer { ID3V2_HEADER_SIZE } else { 0 };
+            start = start.min(length);
+        }
+    }
+    let mut end = length;
+    if end - start >= ID3V1_SIZE {
+        let mut marker = [0u8; 3];
+        reader.seek(SeekFrom::Start(end - ID3V1_SIZE))?;
+        reader.read_exact(&mut marker)?;
+        if &marker == b"TAG" {
+            end -= ID3V1_SIZE;
+        }
+    }
+    if start > 0 {
+        reader.seek(SeekFrom::Start(start))?;
+        let padding = BufReader::new(reader.take(end - start)).bytes()
+            .take_while(|byte| match *byte {
+                Ok(byte) => byte == 0,
+                Err(_) => false,
+            })
+            .count();
+        start += padding as u64;
+    }
+    Ok((start, end))
+}
+
+/// Returns the 64-bit FNV-1a hash of the bytes of a reader. It is computed here, instead of
+/// with the standard library's hasher, so stored hashes do not change between Rust versions.
+fn hash_reader<R: Read>(reader: R) -> io::Result<u64> {
+    let mut reader = BufReader::new(reader);
+    let mut hash = FNV_OFFSET;
+    loop {
+        let length = {
+            let buffer = reader.fill_buf()?;
+            hash = buffer.iter().fold(hash, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+            buffer.len()
+        };
+        if length == 0 {
+            return Ok(hash);
+        }
+        reader.consume(length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn tags_are_not_part_of_the_audio() {
+        let audio = [0xffu8, 0xfb, 0x90, 0x64, 0x00, 0x0f];
+        let mut tagged = b"ID3\x03\x00\x00\x00\x00\x00\x04TIT2".to_vec();
+        tagged.extend_from_slice(&audio);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(ID3V1_SIZE as usize, 0);
+        tagged.extend_from_slice(&id3v1);
+        assert_eq!(audio_range(&mut Cursor::new(&tagged)).unwrap(), (14, 20));
+        assert_eq!(audio_range(&mut Cursor::new(&audio)).unwrap(), (0, 6));
+        assert_eq!(hash_reader(&tagged[14..20]).unwrap(), hash_reader(&audio[..]).unwrap());
+        assert_ne!(hash_reader(&audio[1..]).unwrap(), hash_reader(&audio[..]).unwrap());
+    }
+
+    #[test]
+    fn padding_after_the_tag_is_not_part_of_the_audio() {
+        let mut padded = b"ID3\x04\x00\x00\x00\x00\x00\x04TIT2\x00\x00\x00".to_vec();
+        padded.extend_from_slice(&[0xff, 0xfb, 0x00]);
+        assert_eq!(audio_range(&mut Cursor::new(&padded)).unwrap(), (17, 20));
+        let mut padding_only = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+        padding_only.extend_from_slice(&[0, 0]);
+        assert_eq!(audio_range(&mut Cursor::new(&padding_only)).unwrap(), (12, 12));
+    }
+}
@@ -0,0 +1,83 @@
+use std::path;
+use glob::Pattern;
+use super::get_default_music_folder_path;
+use super::super::error::{Error, Result};
+
+/// The settings of a music library, the `[library]` section of the configuration file: the
+/// directories (roots) where the music is mined, glob patterns of paths that should not be
+/// mined, and whether symbolic links are followed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibrarySettings {
+    pub roots: Vec<path::PathBuf>,
+    pub exclude: Vec<String>,
+    pub follow_symlinks: bool,
+}
+
+impl Default for LibrarySettings {
+
+    /// The default library has the default music folder of the computer as its only root.
+    fn default() -> LibrarySettings {
+        LibrarySettings {
+            roots: get_default_music_folder_path().into_iter().collect(),
+            exclude: Vec::new(),
+            follow_symlinks: false,
+        }
+    }
+}
+
+impl LibrarySettings {
+
+    /// Creates the settings of a library with a single root.
+    pub fn from_dir(directory: &str) -> LibrarySettings {
+        LibrarySettings {
+            roots: vec![path::PathBuf::from(directory)],
+            exclude: Vec::new(),
+            follow_symlinks: false,
+        }
+    }
+
+    /// Returns the compiled exclusion patterns.
+    pub fn patterns(&self) -> Result<Vec<Pattern>> {
+        self.exclude.iter()
+            .map(|exclude| Pattern::new(exclude)
+                .map_err(|e| Error::Parse(format!("Invalid pattern {:?}: {}", exclude, e))))
+            .collect()
+    }
+}
+
+/// Checks if a path matches any of the exclusion patterns. Directories are also matched with a
+/// trailing separator, so "*/Podcasts/*" excludes the Podcasts directory itself.
+pub fn is_excluded(patterns: &[Pattern], path: &path::Path, is_dir: bool) -> bool {
+    let path = path.to_string_lossy();
+    let directory = format!("{}{}", path, path::MAIN_SEPARATOR);
+    patterns.iter().any(|pattern| {
+        pattern.matches(&path) || (is_dir && pattern.matches(&directory))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toml;
+
+    #[test]
+    fn exclusion_patterns() {
+        let mut settings = LibrarySettings::from_dir("/music");
+        settings.exclude = vec![String::from("*/Podcasts/*"), String::from("*.m3u")];
+        let patterns = settings.patterns().unwrap();
+        assert!(is_excluded(&patterns, path::Path::new("/music/Podcasts/episode.mp3"), false));
+        assert!(is_excluded(&patterns, path::Path::new("/music/Podcasts"), true));
+        assert!(is_excluded(&patterns, path::Path::new("/music/list.m3u"), false));
+        assert!(!is_excluded(&patterns, path::Path::new("/music/Rock/song.mp3"), false));
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let mut settings = LibrarySettings::from_dir("/music");
+        settings.exclude = vec![String::from("*/Podcasts/*")];
+        settings.follow_symlinks = true;
+        let content = toml::to_string(&settings).unwrap();
+        assert_eq!(toml::from_str::<LibrarySettings>(&content).unwrap(), settings);
+    }
+}
@@ -2,117 +2,358 @@ extern crate dirs;
 
 /// Music file module.
 pub mod music_file;
+/// Audio hash module.
+pub mod audio_hash;
+/// Library settings module.
+pub mod library;
 
-use super::music_database::MusicDatabase;
+use super::music_database::{MusicDatabase, SongChange};
+use super::config::Config;
+use super::model::{Song, format_duration};
+use super::error::{Error, Result};
 use self::music_file::MusicFile;
-use std::{io, path, fs, sync::mpsc};
+use self::library::LibrarySettings;
+use std::{io, path, fs, fmt, sync::{mpsc, Arc, Mutex, Condvar}, collections::HashSet,
+    time::{Duration, Instant}};
+use glob::Pattern;
 
-/// A miner instance has a directory (where the mine is done), a database connection,
-/// a list of listeners, and the number of files in the directory (scanned and not scanned).
-/// The miner is able use it's database connection to store the music it founds.
+/// A miner instance has the settings of the library (where the mine is done), a database
+/// connection, a list of listeners, the counts of the music files in the library (found,
+/// added, updated, etc.) and the ids of the songs added by the mining. The miner is able use
+/// it's database connection to store the music it founds.
+/// The mining can be paused, resumed or cancelled from other threads through its handle.
 pub struct Miner {
-    directory: path::PathBuf,
+    settings: LibrarySettings,
+    handle: MinerHandle,
+    patterns: Vec<Pattern>,
+    visited: HashSet<path::PathBuf>,
+    skipped: Vec<path::PathBuf>,
     database: MusicDatabase,
     listeners: Vec<mpsc::Sender<MinerEvent>>,
-    number_of_files: f64,
-    files_scanned: f64,
+    counts: MinerCounts,
+    added: Vec<i64>,
+    started: Instant,
 }
 
 impl Miner {
 
-    /// Creates a new instance of a miner, making it's connection to the database available.
-    pub fn new() -> Miner {
-        let path;
-        match get_default_music_folder_path() {
-            Ok(path_buf) => {
-                path = path_buf;
-            },
-            Err(e) => {
-                println!("{:?}", e);
-                panic!();
-            },
-        };
+    /// Creates a new instance of a miner, with the library settings and the database of a
+    /// configuration.
+    pub fn with_config(config: &Config) -> Result<Miner> {
+        Miner::with_settings(config.library.clone(), config.database()?)
+    }
 
-        let mut database = MusicDatabase::new();
-        database.connect().unwrap();
-        Miner {
-            directory: path,
+    /// Creates a new instance of a miner, with specific library settings, that stores the music
+    /// it founds in the given database.
+    pub fn with_settings(settings: LibrarySettings, database: MusicDatabase) -> Result<Miner> {
+        let patterns = settings.patterns()?;
+        Ok(Miner {
+            settings: settings,
+            handle: MinerHandle::new(),
+            patterns: patterns,
+            visited: HashSet::new(),
+            skipped: Vec::new(),
             database: database,
             listeners: Vec::new(),
-            number_of_files: 0.0,
-            files_scanned: 0.0
-        }
+            counts: MinerCounts::default(),
+            added: Vec::new(),
+            started: Instant::now(),
+        })
     }
 
-    /// Returns the current directory where the mining is done.
-    pub fn directory(&self) -> &path::PathBuf {
-        &self.directory
+    /// Returns the library settings, which contain the directories where the mining is done.
+    pub fn settings(&self) -> &LibrarySettings {
+        &self.settings
     }
 
-    /// Creates a new instance of a miner, with an specific mining directory.
-    pub fn from_dir(directory: &str) -> Miner {
-        let mut path = path::PathBuf::new();
-        path.push(directory);
-        let mut database = MusicDatabase::new();
-        database.connect().unwrap();
-        Miner {
-            directory: path,
-            database: database,
-            listeners: Vec::new(),
-            number_of_files: 0.0,
-            files_scanned: 0.0
+    /// Returns a handle to pause, resume or cancel the mining.
+    pub fn handle(&self) -> MinerHandle {
+        self.handle.clone()
+    }
+
+    /// Creates a new instance of a miner, with an specific mining directory, that stores the
+    /// music it founds in the database of a configuration.
+    pub fn from_dir(directory: &str, config: &Config) -> Result<Miner> {
+        Miner::with_settings(LibrarySettings::from_dir(directory), config.database()?)
+    }
+
+    /// Searches music files inside the library roots, and notifies listeners when the mining
+    /// starts, when the music files are found and the mining is running, after every music
+    /// file is processed and when the miner finishes, with a summary of the mining.
+    /// If the mining fails, listeners are notified with the error description. If it is
+    /// cancelled, listeners are notified and the files mined until then remain stored.
+    pub fn mine(&mut self) -> Result<()> {
+        self.counts = MinerCounts::default();
+        self.added.clear();
+        self.started = Instant::now();
+        self.notify_listeners(MinerEvent::Ready);
+        let result = self.mine_roots();
+        match result {
+            Ok(()) => {
+                let summary = self.summary();
+                info!(target: "Miner", "{}", summary);
+                self.notify_listeners(MinerEvent::Finished(summary));
+            },
+            Err(Error::Cancelled) => {
+                info!(target: "Miner", "Mining cancelled");
+                let summary = self.summary();
+                self.notify_listeners(MinerEvent::Cancelled(summary));
+                return Ok(());
+            },
+            Err(ref e) => self.notify_listeners(MinerEvent::Failed(e.to_string())),
+        }
+        result
+    }
 
+    /// Checks the state of the handle between files. While the mining is paused, it waits until
+    /// it is resumed or cancelled; if it is cancelled, `Error::Cancelled` is returned.
+    fn checkpoint(&mut self) -> Result<()> {
+        let handle = self.handle.clone();
+        let (ref state, ref changed) = *handle.state;
+        let mut current = state.lock().unwrap();
+        if *current == MinerState::Paused {
+            info!(target: "Miner", "Mining paused");
+            self.notify_listeners(MinerEvent::Paused);
+            while *current == MinerState::Paused {
+                current = changed.wait(current).unwrap();
+            }
+            if *current == MinerState::Running {
+                info!(target: "Miner", "Mining resumed");
+                self.notify_listeners(MinerEvent::Mining);
+            }
+        }
+        match *current {
+            MinerState::Cancelled => Err(Error::Cancelled),
+            _ => Ok(()),
         }
     }
 
-    /// Searches music files inside the directory, and notifies listeners when the mining is
-    /// running, a music file is stored and when the miner finishes.
-    pub fn mine(&mut self) -> Result<(), io::Error> {
-        let directory = self.directory.clone();
-        self.number_of_files = self.count_files(&directory);
+    /// Collects the music files of every library root, mines them, and then reconciles the
+    /// stored songs that were not found and deletes the albums left without songs.
+    fn mine_roots(&mut self) -> Result<()> {
+        let files = self.collect_files()?;
+        let found: HashSet<path::PathBuf> = files.iter().cloned().collect();
+        self.counts.found = files.len() as u64;
         self.notify_listeners(MinerEvent::Mining);
-        self.mine_from_dir(&directory)?;
-        self.notify_listeners(MinerEvent::Finished);
+        for path in files {
+            self.checkpoint()?;
+            self.save_song(&path)?;
+            let progress = MinerProgress {
+                path: path,
+                counts: self.counts,
+                elapsed: self.started.elapsed(),
+            };
+            info!(target: "Miner", "Percentage mined {:?}", progress.percentage());
+            self.notify_listeners(MinerEvent::Progress(progress));
+        }
+        self.reconcile_missing(&found)?;
+        self.database.delete_empty_albums()
+    }
+
+    /// Reconciles the stored songs that were not found by the walk, including the songs that
+    /// were already missing and the hidden ones. A song inside the library roots whose file
+    /// exists but is excluded now is removed. A song whose file does not exist was moved if a
+    /// song added by the mining has the same audio or tags: the song is moved to that song's
+    /// file, so it keeps its id, rating, counts and playbacks, and stays hidden if it was.
+    /// Otherwise the song is marked as missing, to be located or removed by the user, unless it
+    /// already is; hidden songs are marked too, but they are not offered to the user. Songs
+    /// inside directories that could not be read are kept.
+    fn reconcile_missing(&mut self, found: &HashSet<path::PathBuf>) -> Result<()> {
+        for stored in self.database.stored_songs()? {
+            let song = &stored.song;
+            let in_library = self.settings.roots.iter().any(|root| song.path.starts_with(root));
+            let in_skipped = self.skipped.iter().any(|directory| song.path.starts_with(directory));
+            let exists = song.path.exists();
+            if in_skipped || found.contains(&song.path) || (exists && !in_library) {
+                continue;
+            }
+            self.checkpoint()?;
+            if exists {
+                info!(target: "Miner", "Removing excluded song {:?}", song.path);
+                self.database.delete_song(song.id)?;
+                self.counts.removed += 1;
+                if stored.is_listed() {
+                    self.notify_listeners(MinerEvent::SongRemoved(song.id));
+                }
+            } else if let Some(id_file) = self.database.find_moved(song.id, &self.added)? {
+                info!(target: "Miner", "Song {:?} was moved", song.path);
+                self.database.move_song(song.id, id_file)?;
+                self.added.retain(|id| *id != id_file);
+                self.counts.relocated += 1;
+                self.notify_listeners(MinerEvent::SongRemoved(id_file));
+                if let Some(moved) = self.database.song(song.id)? {
+                    self.notify_listeners(MinerEvent::SongUpdated(moved));
+                }
+            } else if stored.missing_since.is_none() {
+                info!(target: "Miner", "Song {:?} is missing", song.path);
+                self.database.set_missing(song.id)?;
+                self.counts.missing += 1;
+                if !stored.hidden {
+                    self.notify_listeners(MinerEvent::SongRemoved(song.id));
+                }
+            }
+        }
         Ok(())
     }
 
-    /// Mines recursively from an specific directory.
-    pub fn mine_from_dir(&mut self, directory: &path::Path) -> Result<(), io::Error> {
-        info!(target: "Miner", "Searching songs in {:?}", directory);
+    /// Walks the library roots once, and returns the music files found, sorted by path.
+    /// Directories that cannot be read are skipped, and listeners are notified about them; so are
+    /// directories with entries that cannot be read, though their readable entries are walked, so
+    /// the songs stored in them are kept. A directory reached twice through symbolic links is only
+    /// walked once.
+    pub fn collect_files(&mut self) -> Result<Vec<path::PathBuf>> {
+        let mut directories = self.settings.roots.clone();
+        let mut files = Vec::new();
+        self.visited.clear();
+        self.skipped.clear();
+        while let Some(directory) = directories.pop() {
+            self.checkpoint()?;
+            if !self.visit(&directory) {
+                continue;
+            }
+            info!(target: "Miner", "Searching songs in {:?}", directory);
+            let entries = match self.entries(&directory) {
+                Ok((entries, None)) => entries,
+                Ok((entries, Some(e))) => {
+                    self.skip_directory(directory, e);
+                    entries
+                },
+                Err(e) => {
+                    self.skip_directory(directory, e);
+                    continue;
+                },
+            };
+            for (path, is_dir) in entries {
+                if is_dir {
+                    directories.push(path);
+                }
+                else if is_music_file(&path) {
+                    files.push(path);
+                }
+                else {
+                    info!(target: "Miner", "Ignoring {:?}", path);
+                }
+            }
+        }
+        files.sort();
+        info!(target: "Miner", "Songs found: {:?}", files.len());
+        Ok(files)
+    }
+
+    /// Records that a directory could not be read whole, so the songs stored in it are not
+    /// reconciled, and notifies listeners about it.
+    fn skip_directory(&mut self, directory: path::PathBuf, error: Error) {
+        warn!(target: "Miner", "Skipping directory {:?}: {}", directory, error);
+        self.skipped.push(directory.clone());
+        self.counts.skipped += 1;
+        self.notify_listeners(MinerEvent::DirectorySkipped(directory, error.to_string()));
+    }
+
+    /// Marks a directory as visited, returning false if it was already visited through another
+    /// path. Only symbolic links can lead to a directory twice, so the directories are only
+    /// marked when they are followed.
+    fn visit(&mut self, directory: &path::Path) -> bool {
+        if !self.settings.follow_symlinks {
+            return true;
+        }
+        match fs::canonicalize(directory) {
+            Ok(canonical) => self.visited.insert(canonical),
+            Err(_) => true,
+        }
+    }
+
+    /// Returns the paths inside a directory that should be mined, and whether they are
+    /// directories. Excluded paths are skipped, and so are symbolic links unless the library
+    /// follows them. An unreadable directory is an error; unreadable entries are skipped, and the
+    /// error of the last one is returned with the other entries.
+    fn entries(&self, directory: &path::Path)
+        -> Result<(Vec<(path::PathBuf, bool)>, Option<Error>)> {
+        let mut entries = Vec::new();
+        let mut entry_error = None;
         for entry in fs::read_dir(directory)? {
-            let entry = entry?;
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!(target: "Miner", "Skipping an entry of {:?}: {}", directory, e);
+                    entry_error = Some(Error::Io(e));
+                    continue;
+                },
+            };
             let path = entry.path();
-            if path.is_dir() {
-                self.database.save_album(path.clone()).unwrap();
-                self.mine_from_dir(&path)?;
+            let is_symlink = entry.file_type().map(|file_type| file_type.is_symlink())
+                .unwrap_or(false);
+            if is_symlink && !self.settings.follow_symlinks {
+                info!(target: "Miner", "Ignoring symbolic link {:?}", path);
+                continue;
             }
-            else {
-                self.save_song(entry);
-                self.files_scanned = self.files_scanned + 1.0;
-                let percentage = self.files_scanned / self.number_of_files;
-                self.notify_listeners(MinerEvent::Percentage(percentage));
-                info!(target: "Miner", "Percentage mined {:?}", percentage);
+            let is_dir = path.is_dir();
+            if library::is_excluded(&self.patterns, &path, is_dir) {
+                info!(target: "Miner", "Excluding {:?}", path);
+                continue;
             }
+            entries.push((path, is_dir));
         }
-        Ok(())
+        Ok((entries, entry_error))
     }
 
-    /// Given a file, if it is music file, stores its information in database.
-    pub fn save_song(&mut self, file: fs::DirEntry) {
-        let path = file.path();
-        let path_clone = path.clone();
+    /// Given a music file, stores its information in database, counting whether it was added,
+    /// updated or already stored, and notifies listeners with the song added or updated.
+    /// The audio is only hashed if the file was modified since it was stored.
+    /// Files whose tag cannot be read are counted as failed and listeners are notified about
+    /// them; only database errors are returned.
+    pub fn save_song(&mut self, path: &path::Path) -> Result<()> {
+        info!(target: "Miner", "Found song {:?}", path);
+        match MusicFile::from_path(path.to_path_buf()) {
+            Ok(mut music_file) => {
+                let stored_hash = match *music_file.modified() {
+                    Some(modified) => {
+                        self.database.stored_audio_hash(&music_file.path(), modified)?
+                    },
+                    None => None,
+                };
+                match stored_hash {
+                    Some(audio_hash) => music_file.set_audio_hash(Some(audio_hash)),
+                    None => music_file.hash_audio(),
+                }
+                self.save_music_file(music_file)?;
+            },
+            Err(e) => {
+                warn!(target: "Miner", "Skipping {:?}: {}", path, e);
+                self.counts.failed += 1;
+                self.notify_listeners(MinerEvent::FileFailed(path.to_path_buf(), e.to_string()));
+            },
+        }
+        Ok(())
+    }
 
-        match path_clone.as_path().extension() {
-            Some(extension) => {
-                if extension.eq("mp3") {
-                    info!(target: "Miner", "Found song {:?}", path.clone());
-                    self.database.save_song(MusicFile::from_path(path)).unwrap();
+    /// Stores a music file in database, counting whether it was added, updated or already
+    /// stored, and notifies listeners with the song added or updated.
+    fn save_music_file(&mut self, music_file: MusicFile) -> Result<()> {
+        match self.database.save_song(music_file)? {
+            SongChange::Added(id) => {
+                self.counts.added += 1;
+                self.added.push(id);
+                if let Some(song) = self.database.song(id)? {
+                    self.notify_listeners(MinerEvent::SongAdded(song));
                 }
-                else {
-                    info!(target: "Miner", "Ignoring {:?}", path.clone());
+            },
+            SongChange::Updated(id) => {
+                self.counts.updated += 1;
+                if let Some(song) = self.database.song(id)? {
+                    self.notify_listeners(MinerEvent::SongUpdated(song));
                 }
             },
-            None => {}
+            SongChange::Unchanged(_) => self.counts.unchanged += 1,
+        }
+        Ok(())
+    }
+
+    /// Returns the summary of the mining until now.
+    fn summary(&self) -> MinerSummary {
+        MinerSummary {
+            counts: self.counts,
+            elapsed: self.started.elapsed(),
+            skipped_directories: self.skipped.clone(),
         }
     }
 
@@ -123,55 +364,315 @@ impl Miner {
         rx
     }
 
-    /// Notifies the miner's listeners about an event.
+    /// Notifies the miner's listeners about an event. Listeners that were dropped are removed.
     pub fn notify_listeners(&mut self, event: MinerEvent) {
-        for listener in self.listeners.iter_mut() {
-            listener.send(event.clone()).unwrap();
-        }
+        self.listeners.retain(|listener| listener.send(event.clone()).is_ok());
     }
+}
 
-    /// Counts recursively the number of music files in a directory.
-    pub fn count_files(&self, directory: &path::Path) -> f64 {
-        info!(target: "Miner", "Counting songs in {:?}", directory);
-        let mut songs = 0.0;
-        for entry in fs::read_dir(directory).unwrap() {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_dir() {
-                songs = songs + self.count_files(&path);
-            }
-            else {
-                match path.as_path().extension() {
-                    Some(extension) => {
-                        if extension.eq("mp3") {
-                            songs = songs + 1.0;
-                        }
-                    },
-                    None => {}
-                }
-            }
-        }
-        info!(target: "Miner", "Songs found in {:?}: {:?}", directory, songs);
-        songs
-    }
+/// Checks if a file is a music file the miner is able to read.
+fn is_music_file(path: &path::Path) -> bool {
+    path.extension().map_or(false, |extension| extension == "mp3")
 }
 
 /// Returns the default music folder of the computer.
-pub fn get_default_music_folder_path() -> Result<path::PathBuf, io::Error> {
+pub fn get_default_music_folder_path() -> Result<path::PathBuf> {
     if let Some(mut home_dir) = dirs::home_dir() {
         home_dir.push("Music");
         Ok(home_dir.to_path_buf())
     }
     else {
-        Err(io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))
+        Err(Error::Io(io::Error::new(io::ErrorKind::NotFound, "Home directory not found")))
+    }
+}
+
+/// A handle to control a miner from another thread. The miner checks it between files.
+#[derive(Clone)]
+pub struct MinerHandle {
+    state: Arc<(Mutex<MinerState>, Condvar)>,
+}
+
+/// States of a mining controlled through a handle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MinerState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+impl MinerHandle {
+
+    /// Creates a new handle of a running mining.
+    fn new() -> MinerHandle {
+        MinerHandle {
+            state: Arc::new((Mutex::new(MinerState::Running), Condvar::new())),
+        }
+    }
+
+    /// Pauses the mining before the next file, unless it was cancelled.
+    pub fn pause(&self) {
+        self.set_state(MinerState::Paused);
+    }
+
+    /// Resumes a paused mining, unless it was cancelled.
+    pub fn resume(&self) {
+        self.set_state(MinerState::Running);
+    }
+
+    /// Cancels the mining before the next file, even if it is paused. A cancelled mining can
+    /// not be resumed.
+    pub fn cancel(&self) {
+        self.set_state(MinerState::Cancelled);
+    }
+
+    /// Checks if the mining is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.state.0.lock().unwrap() == MinerState::Paused
+    }
+
+    /// Checks if the mining was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.0.lock().unwrap() == MinerState::Cancelled
+    }
+
+    /// Changes the state of the mining, waking up the miner if it is paused.
+    fn set_state(&self, new_state: MinerState) {
+        let (ref state, ref changed) = *self.state;
+        let mut current = state.lock().unwrap();
+        if *current != MinerState::Cancelled {
+            *current = new_state;
+            changed.notify_all();
+        }
+    }
+}
+
+/// Counts of the music files of a mining.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MinerCounts {
+    /// Music files found in the library roots.
+    pub found: u64,
+    /// Songs that were not stored, and were added to the database.
+    pub added: u64,
+    /// Stored songs whose tag changed, and were updated.
+    pub updated: u64,
+    /// Stored songs whose tag did not change.
+    pub unchanged: u64,
+    /// Music files whose tag could not be read.
+    pub failed: u64,
+    /// Stored songs that are excluded now, and were removed from the database.
+    pub removed: u64,
+    /// Stored songs whose file was moved, and were moved to the new file.
+    pub relocated: u64,
+    /// Stored songs whose file was not found, and were marked as missing.
+    pub missing: u64,
+    /// Directories that could not be read whole, and whose stored songs were kept.
+    pub skipped: u64,
+}
+
+impl MinerCounts {
+
+    /// Returns the number of music files processed.
+    pub fn processed(&self) -> u64 {
+        self.added + self.updated + self.unchanged + self.failed
+    }
+}
+
+/// The progress of a mining after a music file is processed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinerProgress {
+    /// The music file processed.
+    pub path: path::PathBuf,
+    pub counts: MinerCounts,
+    pub elapsed: Duration,
+}
+
+impl MinerProgress {
+
+    /// Returns the fraction of the music files processed, between 0 and 1.
+    pub fn percentage(&self) -> f64 {
+        if self.counts.found == 0 {
+            return 1.0;
+        }
+        (self.counts.processed() as f64 / self.counts.found as f64).min(1.0)
+    }
+
+    /// Returns the estimated time until the mining finishes, from the time spent per file until
+    /// now. There is no estimation before the first file is processed.
+    pub fn eta(&self) -> Option<Duration> {
+        let processed = self.counts.processed();
+        if processed == 0 {
+            return None;
+        }
+        let remaining = self.counts.found.saturating_sub(processed);
+        let elapsed = self.elapsed.as_secs() as f64 + self.elapsed.subsec_nanos() as f64 * 1e-9;
+        let seconds = elapsed * remaining as f64 / processed as f64;
+        Some(Duration::from_millis((seconds * 1000.0) as u64))
+    }
+}
+
+impl fmt::Display for MinerProgress {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2}% ({}/{} songs", self.percentage() * 100.0, self.counts.processed(),
+            self.counts.found)?;
+        if let Some(eta) = self.eta() {
+            write!(f, ", {} left", format_duration(eta))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// The summary of a finished or cancelled mining.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinerSummary {
+    pub counts: MinerCounts,
+    pub elapsed: Duration,
+    /// Directories that could not be read whole, whose stored songs were kept.
+    pub skipped_directories: Vec<path::PathBuf>,
+}
+
+impl fmt::Display for MinerSummary {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} songs found in {}: {} added, {} updated, {} unchanged, {} failed, {} removed",
+            self.counts.found, format_duration(self.elapsed), self.counts.added,
+            self.counts.updated, self.counts.unchanged, self.counts.failed, self.counts.removed)?;
+        if self.counts.relocated > 0 || self.counts.missing > 0 {
+            write!(f, ", {} moved, {} missing", self.counts.relocated, self.counts.missing)?;
+        }
+        if self.counts.skipped > 0 {
+            write!(f, ", {} directories skipped", self.counts.skipped)?;
+        }
+        Ok(())
     }
 }
 
 #[derive(Clone, Debug)]
 /// Types of events that occur during the mining.
 pub enum MinerEvent {
+    /// The mining started, and the music files are being searched.
     Ready,
+    /// The music files were found (or the mining was resumed), and they are being mined.
     Mining,
-    Percentage(f64),
-    Finished,
+    /// A music file was processed.
+    Progress(MinerProgress),
+    /// A song was added to the database.
+    SongAdded(Song),
+    /// A stored song was updated.
+    SongUpdated(Song),
+    /// The stored song with the given id was removed, moved or is missing, so it is not listed.
+    SongRemoved(i64),
+    /// The tag of a music file could not be read.
+    FileFailed(path::PathBuf, String),
+    /// A directory, or some of its entries, could not be read, so its music files may not have
+    /// been mined, and its stored songs were kept.
+    DirectorySkipped(path::PathBuf, String),
+    Paused,
+    Cancelled(MinerSummary),
+    Finished(MinerSummary),
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_estimates_remaining_time() {
+        let mut progress = MinerProgress {
+            path: path::PathBuf::from("song.mp3"),
+            counts: MinerCounts { found: 4, ..MinerCounts::default() },
+            elapsed: Duration::from_secs(10),
+        };
+        assert_eq!(progress.eta(), None);
+        progress.counts.added = 1;
+        assert_eq!(progress.percentage(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(30)));
+        assert_eq!(progress.to_string(), "25.00% (1/4 songs, 0:30 left)");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_survives_symlink_loops_and_missing_roots() {
+        use std::{env, process, os::unix::fs::symlink};
+        let root = env::temp_dir().join(format!("music_player_rs_walk_{}", process::id()));
+        let album = root.join("album");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(album.join("song.mp3"), "").unwrap();
+        fs::write(album.join("cover.jpg"), "").unwrap();
+        symlink(&root, album.join("loop")).unwrap();
+
+        let mut settings = LibrarySettings::from_dir(&root.to_string_lossy());
+        settings.roots.push(root.join("missing"));
+        settings.follow_symlinks = true;
+        let mut database = MusicDatabase::new();
+        database.with_database(":memory:");
+        database.connect().unwrap();
+        let mut miner = Miner::with_settings(settings, database).unwrap();
+        let files = miner.collect_files();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(files.unwrap(), vec![album.join("song.mp3")]);
+        assert_eq!(miner.skipped, vec![root.join("missing")]);
+        assert_eq!(miner.counts.skipped, 1);
+        assert!(miner.summary().to_string().ends_with(", 1 directories skipped"));
+    }
+
+    #[test]
+    fn moved_songs_keep_their_statistics() {
+        use std::{env, process};
+        use super::super::model::Period;
+        let root = env::temp_dir().join(format!("music_player_rs_move_{}", process::id()));
+        let outside = root.with_extension("mp3");
+        let first = root.join("a").join("song.mp3");
+        let second = root.join("b").join("moved.mp3");
+        fs::create_dir_all(first.parent().unwrap()).unwrap();
+        fs::create_dir_all(second.parent().unwrap()).unwrap();
+        fs::write(&first, [0xffu8, 0xfb, 0x90, 0x64, 0x00, 0x0f]).unwrap();
+        let mut tag = ::id3::Tag::new();
+        tag.set_title("Song");
+        tag.set_artist("Artist");
+        tag.write_to_path(&first, ::id3::Version::Id3v24).unwrap();
+
+        let mut database = MusicDatabase::new();
+        database.with_database(":memory:");
+        database.connect().unwrap();
+        let settings = LibrarySettings::from_dir(&root.to_string_lossy());
+        let mut miner = Miner::with_settings(settings, database).unwrap();
+        let mine = |miner: &mut Miner| {
+            miner.mine().unwrap();
+            miner.counts
+        };
+        mine(&mut miner);
+        let id = miner.database.songs().unwrap()[0].id;
+        miner.database.set_rating(id, 4).unwrap();
+        miner.database.record_playback(id, 1_000, Duration::from_secs(60), true).unwrap();
+
+        fs::rename(&first, &second).unwrap();
+        let moved = mine(&mut miner);
+        fs::rename(&second, &outside).unwrap();
+        let gone = mine(&mut miner);
+        fs::rename(&outside, &first).unwrap();
+        let back = mine(&mut miner);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!((moved.added, moved.relocated, moved.missing), (1, 1, 0));
+        assert_eq!((gone.relocated, gone.missing), (0, 1));
+        assert_eq!((back.added, back.relocated, back.missing), (1, 1, 0));
+        let songs = miner.database.songs().unwrap();
+        assert_eq!(songs.len(), 1);
+        assert_eq!((songs[0].id, songs[0].path.clone()), (id, first));
+        assert_eq!((songs[0].rating, songs[0].play_count), (4, 1));
+        assert_eq!(miner.database.history(&Period::all(), None).unwrap()[0].song.id, id);
+        assert!(miner.database.missing_songs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cancelled_mining_is_not_resumed() {
+        let handle = MinerHandle::new();
+        handle.pause();
+        assert!(handle.is_paused());
+        handle.cancel();
+        handle.resume();
+        assert!(handle.is_cancelled());
+    }
 }
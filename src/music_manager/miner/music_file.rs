@@ -1,37 +1,74 @@
-use std::{path};
+use std::{fs, path, time::UNIX_EPOCH};
 use id3::{Tag, Timestamp};
+use super::audio_hash::{audio_hash, audio_range};
+use super::super::error::Result;
 
 /// A music file has the music file path and the important information about a song, such as
 /// artist, title, album, etc.
 pub struct MusicFile {
     path: path::PathBuf,
     artist: Option<String>,
+    album_artist: Option<String>,
     title: Option<String>,
     album: Option<String>,
     date_recorded: Option<Timestamp>,
+    year: Option<i32>,
     genre: Option<String>,
     track: Option<u32>,
+    duration: Option<u32>,
+    disc: Option<u32>,
+    audio_hash: Option<String>,
+    modified: Option<i64>,
+    bitrate: Option<u32>,
 }
 
 impl MusicFile {
 
-    /// Creates a new instance of a music file given the path of the file.
-    pub fn from_path(path: path::PathBuf) -> MusicFile {
-        let tag = Tag::read_from_path(path.clone()).unwrap();
+    /// Creates a new instance of a music file given the path of the file, reading its tag,
+    /// when it was last modified and the bitrate of its audio. Only the tag is read: the audio
+    /// is hashed by `hash_audio`.
+    pub fn from_path(path: path::PathBuf) -> Result<MusicFile> {
+        let tag = Tag::read_from_path(path.clone())?;
+        let mut music_file = MusicFile::from_tag(path, &tag);
+        music_file.modified = fs::metadata(&music_file.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs() as i64);
+        let audio_size = fs::File::open(&music_file.path)
+            .and_then(|mut file| audio_range(&mut file))
+            .map(|(start, end)| end - start);
+        music_file.bitrate = match (audio_size, music_file.duration) {
+            (Ok(audio_size), Some(duration)) => bitrate(audio_size, duration),
+            _ => None,
+        };
+        Ok(music_file)
+    }
+
+    /// Creates a new instance of a music file given the path of the file and its tag, without
+    /// an audio hash, a modification time or a bitrate.
+    pub fn from_tag(path: path::PathBuf, tag: &Tag) -> MusicFile {
         MusicFile {
             path: path,
             artist: tag.artist().map(str::to_string),
+            album_artist: tag.album_artist().map(str::to_string),
             title: tag.title().map(str::to_string),
             album: tag.album().map(str::to_string),
             date_recorded: tag.date_recorded(),
+            year: tag.year().or(tag.date_recorded().map(|date| date.year)),
             genre: tag.genre().map(str::to_string),
             track: tag.track(),
+            duration: tag.duration(),
+            disc: tag.disc(),
+            audio_hash: None,
+            modified: None,
+            bitrate: None,
         }
     }
 
     /// Returns the music file's path.
     pub fn path(&self) -> String {
-        self.path.to_str().unwrap().to_string()
+        self.path.to_string_lossy().into_owned()
     }
 
     /// Returns the music file's artist.
@@ -39,6 +76,11 @@ impl MusicFile {
         &self.artist
     }
 
+    /// Returns the music file's album artist.
+    pub fn album_artist(&self) -> &Option<String> {
+        &self.album_artist
+    }
+
     /// Returns the music file's title.
     pub fn title(&self) -> &Option<String> {
         &self.title
@@ -54,6 +96,11 @@ impl MusicFile {
         &self.date_recorded
     }
 
+    /// Returns the music file's year, taken from the year or the recording date frames.
+    pub fn year(&self) -> &Option<i32> {
+        &self.year
+    }
+
     /// Returns the music file's genre.
     pub fn genre(&self) -> &Option<String> {
         &self.genre
@@ -64,4 +111,107 @@ impl MusicFile {
         &self.track
     }
 
+    /// Returns the music file's duration in milliseconds.
+    pub fn duration(&self) -> &Option<u32> {
+        &self.duration
+    }
+
+    /// Returns the music file's disc number, for albums with more than one disc.
+    pub fn disc(&self) -> &Option<u32> {
+        &self.disc
+    }
+
+    /// Returns the hash of the music file's audio, without its tags.
+    pub fn audio_hash(&self) -> &Option<String> {
+        &self.audio_hash
+    }
+
+    /// Sets the hash of the music file's audio.
+    pub fn set_audio_hash(&mut self, audio_hash: Option<String>) {
+        self.audio_hash = audio_hash;
+    }
+
+    /// Hashes the music file's audio, reading the whole file. A file whose audio cannot be read
+    /// has no audio hash.
+    pub fn hash_audio(&mut self) {
+        self.audio_hash = audio_hash(&self.path).ok();
+    }
+
+    /// Returns when the music file was last modified, in seconds since the Unix epoch.
+    pub fn modified(&self) -> &Option<i64> {
+        &self.modified
+    }
+
+    /// Returns the average bitrate of the music file's audio in kbit/s, computed from the size
+    /// of its audio, without its tags, and its duration.
+    pub fn bitrate(&self) -> &Option<u32> {
+        &self.bitrate
+    }
+
+    /// Returns the directory of the music file's album. Discs of an album are usually stored in
+    /// subdirectories such as "CD1" or "Disc 2", so those are considered part of their parent.
+    pub fn album_directory(&self) -> path::PathBuf {
+        let directory = match self.path.parent() {
+            Some(directory) => directory.to_path_buf(),
+            None => return path::PathBuf::new(),
+        };
+        let is_disc_directory = directory.file_name()
+            .map(|name| is_disc_directory(&name.to_string_lossy()))
+            .unwrap_or(false);
+        match directory.parent() {
+            Some(parent) if is_disc_directory => parent.to_path_buf(),
+            _ => directory,
+        }
+    }
+
+}
+
+/// Returns the average bitrate in kbit/s of audio of a size in bytes and a duration in
+/// milliseconds. Audio without duration has none.
+fn bitrate(audio_size: u64, duration: u32) -> Option<u32> {
+    if duration == 0 {
+        return None;
+    }
+    Some((audio_size * 8 / u64::from(duration)) as u32)
+}
+
+/// Checks if a directory name is the name of an album's disc, like "CD1", "cd 2" or "Disc 03".
+fn is_disc_directory(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    for prefix in &["cd", "disc", "disk"] {
+        if name.starts_with(prefix) {
+            let number = name[prefix.len()..].trim();
+            return !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn disc_directories() {
+        assert!(is_disc_directory("CD1"));
+        assert!(is_disc_directory("Disc 02"));
+        assert!(is_disc_directory("disk3"));
+        assert!(!is_disc_directory("Discovery"));
+        assert!(!is_disc_directory("CD"));
+    }
+
+    #[test]
+    fn bitrate_is_computed_from_the_audio() {
+        let path = env::temp_dir().join(format!("music_player_rs_bitrate_{}.mp3", process::id()));
+        fs::write(&path, vec![0xffu8; 16_000]).unwrap();
+        let mut tag = Tag::new();
+        tag.set_title("Song");
+        tag.set_duration(1_000);
+        tag.write_to_path(&path, ::id3::Version::Id3v24).unwrap();
+        let music_file = MusicFile::from_path(path.clone());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(*music_file.unwrap().bitrate(), Some(128));
+        assert_eq!(bitrate(16_000, 0), None);
+    }
 }
@@ -1,10 +1,22 @@
 //! Library to create a music player and/or a songs miner
 
+/// Error module
+pub mod error;
+/// Configuration module
+pub mod config;
+/// Model module
+pub mod model;
 /// Database module
 pub mod music_database;
+/// Schema module
+pub mod schema;
 /// Query manager module
 pub mod query_manager;
 /// Miner module
 pub mod miner;
 /// Search manager module
 pub mod search_manager;
+/// Cover art module
+pub mod cover;
+
+pub use self::error::{Error, Result};
@@ -0,0 +1,568 @@
+use std::{path::PathBuf, result, time::{Duration, SystemTime, UNIX_EPOCH}};
+use serde::Serializer;
+use super::error::{Error, Result};
+
+/// A song ("rola") stored in the database, with its performer and album.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Song {
+    pub id: i64,
+    pub performer: Performer,
+    pub album: Album,
+    pub path: PathBuf,
+    pub title: String,
+    pub track: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    #[serde(serialize_with = "serialize_optional_millis")]
+    pub duration: Option<Duration>,
+    pub disc: Option<u32>,
+    /// Average bitrate of the song's audio in kbit/s, computed when its file was mined.
+    pub bitrate: Option<u32>,
+    pub rating: u8,
+    pub play_count: u32,
+    pub skip_count: u32,
+    pub last_played: Option<i64>,
+    pub date_added: Option<i64>,
+}
+
+impl Song {
+
+    /// Returns the format of the song's file: its extension, in uppercase.
+    pub fn format(&self) -> Option<String> {
+        self.path.extension().map(|extension| extension.to_string_lossy().to_uppercase())
+    }
+}
+
+/// Fields of a song that can be shown as columns of the songs' list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SongField {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Track,
+    Disc,
+    Year,
+    Duration,
+    Path,
+    Format,
+    Rating,
+    PlayCount,
+    SkipCount,
+    LastPlayed,
+    DateAdded,
+}
+
+impl SongField {
+
+    /// Every field, in the order they are offered to the user.
+    pub const ALL: [SongField; 15] = [SongField::Title, SongField::Artist, SongField::Album,
+        SongField::Genre, SongField::Track, SongField::Disc, SongField::Year,
+        SongField::Duration, SongField::Path, SongField::Format, SongField::Rating,
+        SongField::PlayCount, SongField::SkipCount, SongField::LastPlayed, SongField::DateAdded];
+
+    /// Returns the title of the field's column.
+    pub fn title(&self) -> &'static str {
+        match self {
+            SongField::Title => "Title",
+            SongField::Artist => "Artist",
+            SongField::Album => "Album",
+            SongField::Genre => "Genre",
+            SongField::Track => "Track",
+            SongField::Disc => "Disc",
+            SongField::Year => "Year",
+            SongField::Duration => "Duration",
+            SongField::Path => "Path",
+            SongField::Format => "Format",
+            SongField::Rating => "Rating",
+            SongField::PlayCount => "Plays",
+            SongField::SkipCount => "Skips",
+            SongField::LastPlayed => "Last played",
+            SongField::DateAdded => "Added",
+        }
+    }
+
+    /// Checks if the field is sorted as a number rather than as text.
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            SongField::Title | SongField::Artist | SongField::Album | SongField::Genre
+                | SongField::Path | SongField::Format => false,
+            _ => true,
+        }
+    }
+
+    /// Returns the value of a numeric field of a song, if it has one.
+    pub fn number(&self, song: &Song) -> Option<i64> {
+        match self {
+            SongField::Track => song.track.map(i64::from),
+            SongField::Disc => song.disc.map(i64::from),
+            SongField::Year => song.year.map(i64::from),
+            SongField::Duration => song.duration.map(|duration| millis(duration) as i64),
+            SongField::Rating => Some(i64::from(song.rating)),
+            SongField::PlayCount => Some(i64::from(song.play_count)),
+            SongField::SkipCount => Some(i64::from(song.skip_count)),
+            SongField::LastPlayed => song.last_played,
+            SongField::DateAdded => song.date_added,
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the field of a song as text, empty if it has none.
+    pub fn text(&self, song: &Song) -> String {
+        match self {
+            SongField::Title => song.title.clone(),
+            SongField::Artist => song.performer.name.clone(),
+            SongField::Album => song.album.name.clone(),
+            SongField::Genre => song.genre.clone().unwrap_or_default(),
+            SongField::Track | SongField::Disc | SongField::Year | SongField::PlayCount
+                | SongField::SkipCount => {
+                self.number(song).map(|number| number.to_string()).unwrap_or_default()
+            },
+            SongField::Rating => "★".repeat(song.rating as usize),
+            SongField::LastPlayed | SongField::DateAdded => {
+                self.number(song).map(format_date).unwrap_or_default()
+            },
+            SongField::Duration => song.duration.map(format_duration).unwrap_or_default(),
+            SongField::Path => song.path.to_string_lossy().into_owned(),
+            SongField::Format => song.format().unwrap_or_default(),
+        }
+    }
+}
+
+/// Highest rating of a song; unrated songs have rating 0.
+pub const MAX_RATING: u8 = 5;
+
+/// Returns the current time, in seconds since the Unix epoch.
+pub fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Formats a timestamp, in seconds since the Unix epoch, as a date "YYYY-MM-DD" in UTC.
+pub fn format_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Returns the year, month and day of a timestamp, in seconds since the Unix epoch, in UTC.
+pub fn civil_date(timestamp: i64) -> (i64, i64, i64) {
+    // Civil date from the number of days since the epoch, by Howard Hinnant's algorithm.
+    let days = if timestamp >= 0 { timestamp } else { timestamp - 86_399 } / 86_400 + 719_468;
+    let era = if days >= 0 { days } else { days - 146_096 } / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Returns the timestamp, in seconds since the Unix epoch, of the start of a day in UTC. It is
+/// the inverse of `civil_date`.
+pub fn date_timestamp(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era - 719_468) * 86_400
+}
+
+/// A period of time, from a timestamp (included) to another (excluded), in seconds since the
+/// Unix epoch. Months and years start at midnight UTC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Period {
+    pub from: i64,
+    pub to: i64,
+}
+
+impl Period {
+
+    /// The period with every time.
+    pub fn all() -> Period {
+        Period { from: i64::min_value(), to: i64::max_value() }
+    }
+
+    /// The period of a year.
+    pub fn year(year: i64) -> Period {
+        Period { from: date_timestamp(year, 1, 1), to: date_timestamp(year + 1, 1, 1) }
+    }
+
+    /// The period of a month, from 1 to 12, of a year.
+    pub fn month(year: i64, month: i64) -> Period {
+        let (next_year, next_month) = if month >= 12 { (year + 1, 1) } else { (year, month + 1) };
+        Period {
+            from: date_timestamp(year, month, 1),
+            to: date_timestamp(next_year, next_month, 1),
+        }
+    }
+
+    /// The period of the day of a timestamp.
+    pub fn day(timestamp: i64) -> Period {
+        let (year, month, day) = civil_date(timestamp);
+        let from = date_timestamp(year, month, day);
+        Period { from: from, to: from + 86_400 }
+    }
+
+    /// Parses a period relative to a timestamp: "all", "today", "this-month", "this-year", a
+    /// year ("2026") or a month ("2026-10").
+    pub fn parse(text: &str, now: i64) -> Result<Period> {
+        let (year, month, _) = civil_date(now);
+        let invalid = || Error::Parse(format!("Invalid period {:?}", text));
+        match text {
+            "all" => Ok(Period::all()),
+            "today" => Ok(Period::day(now)),
+            "this-month" => Ok(Period::month(year, month)),
+            "this-year" => Ok(Period::year(year)),
+            _ => {
+                let mut parts = text.splitn(2, '-');
+                let year = parts.next().and_then(|year| year.parse::<i64>().ok())
+                    .ok_or_else(invalid)?;
+                match parts.next() {
+                    Some(month) => match month.parse::<i64>() {
+                        Ok(month) if month >= 1 && month <= 12 => Ok(Period::month(year, month)),
+                        _ => Err(invalid()),
+                    },
+                    None => Ok(Period::year(year)),
+                }
+            },
+        }
+    }
+
+    /// Checks if a timestamp is in the period.
+    pub fn contains(&self, timestamp: i64) -> bool {
+        self.from <= timestamp && timestamp < self.to
+    }
+}
+
+/// A playback of a song in the listening history: when it started, for how long the song was
+/// listened and whether it was listened until its end.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Playback {
+    pub id: i64,
+    pub song: Song,
+    pub started: i64,
+    #[serde(serialize_with = "serialize_millis")]
+    pub listened: Duration,
+    pub completed: bool,
+}
+
+/// The listening of a day of the history: its date, its number of playbacks and the time
+/// listened.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DayListening {
+    pub date: String,
+    pub playbacks: i64,
+    #[serde(serialize_with = "serialize_millis")]
+    pub listened: Duration,
+}
+
+/// Statistics of the library, to audit it: its number of songs, albums, performers and genres,
+/// the duration and size of its songs, their number per format (file extension) and per year,
+/// and the number of songs with missing tags or without cover art. They are the statistics of
+/// the listed songs: the hidden and the missing songs are only counted by `hidden` and
+/// `missing`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LibraryStatistics {
+    pub songs: i64,
+    pub albums: i64,
+    pub performers: i64,
+    pub genres: i64,
+    pub hidden: i64,
+    pub missing: i64,
+    #[serde(serialize_with = "serialize_millis")]
+    pub duration: Duration,
+    pub size: u64,
+    pub formats: Vec<(String, i64)>,
+    pub missing_tags: i64,
+    pub missing_art: i64,
+    pub years: Vec<(i32, i64)>,
+}
+
+/// A group of songs that are likely copies of the same recording, and the reason they are.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DuplicateGroup {
+    pub reason: DuplicateReason,
+    pub songs: Vec<Song>,
+}
+
+/// Why songs are considered copies of the same recording.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateReason {
+    /// Their audio, without the tags, is identical.
+    SameAudio,
+    /// Their performer and title are the same, ignoring case and punctuation, and their
+    /// durations are close.
+    SameMetadata,
+}
+
+impl DuplicateReason {
+
+    /// Returns the description of the reason, to be shown to the user.
+    pub fn title(&self) -> &'static str {
+        match self {
+            DuplicateReason::SameAudio => "Same audio",
+            DuplicateReason::SameMetadata => "Same performer, title and length",
+        }
+    }
+}
+
+/// A smart playlist: its songs are the ones that match its rules, a query of the search
+/// manager's language, so they change with the library. They are listed in the playlist's
+/// order, at most `limit` if there is one.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SmartPlaylist {
+    pub id: i64,
+    pub name: String,
+    pub rules: String,
+    pub order: PlaylistOrder,
+    pub limit: Option<i64>,
+}
+
+/// A search saved with a name, to be run again.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub query: String,
+}
+
+/// Orders of the songs of a smart playlist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlaylistOrder {
+    Title,
+    Random,
+    MostPlayed,
+    LeastPlayed,
+    TopRated,
+    RecentlyAdded,
+    RecentlyPlayed,
+}
+
+impl PlaylistOrder {
+
+    /// All the orders, as offered to the user.
+    pub const ALL: [PlaylistOrder; 7] = [
+        PlaylistOrder::Title,
+        PlaylistOrder::Random,
+        PlaylistOrder::MostPlayed,
+        PlaylistOrder::LeastPlayed,
+        PlaylistOrder::TopRated,
+        PlaylistOrder::RecentlyAdded,
+        PlaylistOrder::RecentlyPlayed,
+    ];
+
+    /// Returns the name of the order, as stored in the database and written in the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlaylistOrder::Title => "title",
+            PlaylistOrder::Random => "random",
+            PlaylistOrder::MostPlayed => "most-played",
+            PlaylistOrder::LeastPlayed => "least-played",
+            PlaylistOrder::TopRated => "top-rated",
+            PlaylistOrder::RecentlyAdded => "recently-added",
+            PlaylistOrder::RecentlyPlayed => "recently-played",
+        }
+    }
+
+    /// Returns the label of the order shown in the GUI.
+    pub fn title(&self) -> &'static str {
+        match self {
+            PlaylistOrder::Title => "Title",
+            PlaylistOrder::Random => "Random",
+            PlaylistOrder::MostPlayed => "Most played",
+            PlaylistOrder::LeastPlayed => "Least played",
+            PlaylistOrder::TopRated => "Top rated",
+            PlaylistOrder::RecentlyAdded => "Recently added",
+            PlaylistOrder::RecentlyPlayed => "Recently played",
+        }
+    }
+
+    /// Given the name of an order, returns the corresponding order.
+    pub fn from_name(name: &str) -> Result<PlaylistOrder> {
+        match PlaylistOrder::ALL.iter().find(|order| order.name() == name) {
+            Some(order) => Ok(*order),
+            None => Err(Error::Parse(format!("Unknown playlist order {:?}", name))),
+        }
+    }
+}
+
+/// An album stored in the database. An album is identified by its name, the performer of the
+/// album (its album artist) and the directory where it is stored.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Album {
+    pub id: i64,
+    pub path: Option<PathBuf>,
+    pub name: String,
+    pub year: Option<i32>,
+    pub id_performer: Option<i64>,
+}
+
+/// An album with the name of its performer, its number of songs and the path of one of its
+/// songs, as shown in the album grid.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AlbumEntry {
+    pub album: Album,
+    pub performer: Option<String>,
+    pub songs: i64,
+    pub song_path: Option<PathBuf>,
+}
+
+/// A performer stored in the database, which may be a person, a group or unknown.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Performer {
+    pub id: i64,
+    pub kind: PerformerType,
+    pub name: String,
+}
+
+/// A person, that may be a member of one or more groups.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Person {
+    pub id: i64,
+    pub stage_name: Option<String>,
+    pub real_name: Option<String>,
+    pub birth_date: Option<String>,
+    pub death_date: Option<String>,
+}
+
+/// A group of persons.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Group {
+    pub id: i64,
+    pub name: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+/// Types of performers, as stored in the types table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum PerformerType {
+    Person,
+    Group,
+    Unknown,
+}
+
+impl PerformerType {
+
+    /// Returns the performer type given its id in the types table.
+    pub fn from_id(id_type: i64) -> PerformerType {
+        match id_type {
+            0 => PerformerType::Person,
+            1 => PerformerType::Group,
+            _ => PerformerType::Unknown,
+        }
+    }
+
+    /// Returns the id of the performer type in the types table.
+    pub fn id(&self) -> i64 {
+        match self {
+            PerformerType::Person => 0,
+            PerformerType::Group => 1,
+            PerformerType::Unknown => 2,
+        }
+    }
+}
+
+/// Returns a duration in milliseconds.
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_millis())
+}
+
+/// Serializes a duration as its number of milliseconds, as it is stored in the database.
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S)
+    -> result::Result<S::Ok, S::Error> {
+    serializer.serialize_u64(millis(*duration))
+}
+
+/// Serializes an optional duration as its number of milliseconds.
+fn serialize_optional_millis<S: Serializer>(duration: &Option<Duration>, serializer: S)
+    -> result::Result<S::Ok, S::Error> {
+    match *duration {
+        Some(duration) => serializer.serialize_some(&millis(duration)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Formats a duration as minutes and seconds, e.g. "2:05".
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Formats a long duration in days, hours and minutes, e.g. "2 d 5 h 12 min".
+pub fn format_long_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{} min", minutes),
+        (0, _) => format!("{} h {} min", hours, minutes),
+        _ => format!("{} d {} h {} min", days, hours, minutes),
+    }
+}
+
+/// Formats a size in bytes with a binary unit, e.g. "1.5 GiB".
+pub fn format_size(size: u64) -> String {
+    let units = ["bytes", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < units.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn dates_are_formatted_in_utc() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+        assert_eq!(format_date(-1), "1969-12-31");
+    }
+
+    #[test]
+    fn periods_are_parsed() {
+        let now = 1_700_000_000;
+        assert_eq!(Period::parse("this-month", now).unwrap(), Period::month(2023, 11));
+        assert_eq!(Period::parse("2026", now).unwrap(), Period::year(2026));
+        let december = Period::parse("2026-12", now).unwrap();
+        assert_eq!(format_date(december.from), "2026-12-01");
+        assert_eq!(format_date(december.to), "2027-01-01");
+        assert!(Period::day(now).contains(now));
+        assert!(!Period::day(now).contains(now + 86_400));
+        assert!(Period::parse("2026-13", now).is_err());
+        assert!(Period::parse("soon", now).is_err());
+        for &timestamp in [-86_400 * 800, 0, 951_782_400, now].iter() {
+            let (year, month, day) = civil_date(timestamp);
+            assert_eq!(date_timestamp(year, month, day), Period::day(timestamp).from);
+        }
+    }
+
+    #[test]
+    fn durations_are_serialized_in_milliseconds() {
+        let day = DayListening {
+            date: String::from("2026-10-19"),
+            playbacks: 2,
+            listened: Duration::from_millis(90_500),
+        };
+        assert_eq!(serde_json::to_string(&day).unwrap(),
+            "{\"date\":\"2026-10-19\",\"playbacks\":2,\"listened\":90500}");
+    }
+}
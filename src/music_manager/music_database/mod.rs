@@ -1,17 +1,118 @@
-use super::{query_manager, miner::music_file::MusicFile};
+use super::{query_manager, schema, miner::music_file::MusicFile};
+use super::cover::{cover_file, embedded_cover};
+use super::schema::{
+    rolas, performers, albums, persons, groups, history, playlists, searches, recent_searches,
+};
+use super::error::{Error, Result};
+use super::model::{
+    MAX_RATING, Song, Album, AlbumEntry, Performer, PerformerType, Person, Group, Period, Playback,
+    DayListening, DuplicateGroup, DuplicateReason, LibraryStatistics, SmartPlaylist, PlaylistOrder,
+    SavedSearch, format_date, now,
+};
 use super::query_manager::{
     TableColumn as TC,
     TableColumn::Rolas as Rolas,
     TableColumn::Performers as Performers,
     TableColumn::Albums as Albums,
-    Conditional::Eq,
-    Conditional::EqVal,
+    TableColumn::Persons as Persons,
+    TableColumn::Groups as Groups,
+    TableColumn::History as History,
+    TableColumn::Playlists as Playlists,
+    TableColumn::Searches as Searches,
+    TableColumn::RecentSearches as RecentSearches,
+    Conditional::{self, EqVal, IsNull, Ge, Lt, StartsWith, In},
+    Query, Select, Insert, Update, Delete, Field, Order, Join, Value, Table,
 };
-use std::{path, collections::HashMap};
-use id3::Timestamp;
+use std::{collections::{BTreeMap, HashMap}, fs, path, time::Duration};
 use sqlite;
 
-type SQLiteError = sqlite::Error;
+/// A filter of songs by genre, performer and album, as chosen in a column browser. Every
+/// value that is present must match.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SongFilter {
+    pub genre: Option<String>,
+    pub id_performer: Option<i64>,
+    pub id_album: Option<i64>,
+}
+
+impl SongFilter {
+
+    /// Returns the conditionals over the "rolas" table of the filter. Hidden and missing songs
+    /// never match.
+    pub fn conditionals(&self) -> Vec<Conditional> {
+        let mut conditionals = Vec::new();
+        if let Some(ref genre) = self.genre {
+            conditionals.push(EqVal(Rolas(rolas::Genre), Value::String(genre.clone())));
+        }
+        if let Some(id_performer) = self.id_performer {
+            conditionals.push(EqVal(Rolas(rolas::IdPerformer), Value::Integer(id_performer)));
+        }
+        if let Some(id_album) = self.id_album {
+            conditionals.push(EqVal(Rolas(rolas::IdAlbum), Value::Integer(id_album)));
+        }
+        conditionals.push(visible());
+        conditionals
+    }
+}
+
+/// The change made to the database when a music file is saved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SongChange {
+    /// A new song was inserted, with the given id.
+    Added(i64),
+    /// The song with the given id was stored, and it was updated with the file's tag.
+    Updated(i64),
+    /// The song with the given id was stored, and it already matched the file's tag.
+    Unchanged(i64),
+}
+
+/// A stored song, whether it is listed or not, as the miner reconciles it with the files it
+/// finds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredSong {
+    pub song: Song,
+    /// Whether the song was hidden, as a copy of another one.
+    pub hidden: bool,
+    /// When the song's file was not found, if it is missing.
+    pub missing_since: Option<i64>,
+}
+
+impl StoredSong {
+
+    /// Checks if the song is listed, that is, it is neither hidden nor missing.
+    pub fn is_listed(&self) -> bool {
+        !self.hidden && self.missing_since.is_none()
+    }
+}
+
+/// The rows resulting from a statement. Unlike `sqlite::Cursor`, which reads the values of every
+/// row with the types of the first row's values, every value is read with its own type, so a
+/// column can be NULL in some rows only.
+pub struct Rows<'l> {
+    statement: sqlite::Statement<'l>,
+    values: Vec<Value>,
+    done: bool,
+}
+
+impl<'l> Rows<'l> {
+
+    /// Advances to the next row and reads all its values.
+    pub fn next(&mut self) -> Result<Option<&[Value]>> {
+        if self.done {
+            return Ok(None);
+        }
+        if self.statement.next()? == sqlite::State::Done {
+            self.done = true;
+            return Ok(None);
+        }
+        self.values.clear();
+        for i in 0..self.statement.columns() {
+            let value = self.statement.read::<Value>(i)?;
+            self.values.push(value);
+        }
+        Ok(Some(&self.values))
+    }
+}
 
 /// A music database stores the connection to the SQLite database and its name.
 pub struct MusicDatabase {
@@ -36,8 +137,10 @@ impl MusicDatabase {
         self
     }
 
-    /// Tries to connect to the SQLite database.
-    pub fn connect(&mut self) -> Result<(), SQLiteError> {
+    /// Tries to connect to the SQLite database. The database is shared by the GUI and the miner,
+    /// so it is written ahead of a log, and a write waits for another one to finish instead of
+    /// failing while the database is busy.
+    pub fn connect(&mut self) -> Result<()> {
         let database = match self.database {
             Some(ref database) => &database[..],
             None => "./music_player_rs.db",
@@ -45,212 +148,1933 @@ impl MusicDatabase {
         let database_path = path::Path::new(database);
         let create_database = !database_path.exists();
         info!(target: "MusicDatabase", "Connecting to {:?}", database_path);
-        self.connection = Some(sqlite::open(database_path)?);
+        let mut connection = sqlite::open(database_path)?;
+        connection.set_busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute("PRAGMA journal_mode = WAL;")?;
+        self.connection = Some(connection);
         info!(target: "MusicDatabase", "Succesfully connected to database");
         if create_database {
-            self.execute(&query_manager::create_database().unwrap())?;
+            self.execute(schema::TABLES)?;
+            self.set_version(schema::MIGRATIONS.len() as i64)?;
+        }
+        else {
+            self.migrate()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the schema version of the database.
+    pub fn version(&self) -> Result<i64> {
+        let mut cursor = self.query("PRAGMA user_version;")?;
+        match cursor.next()? {
+            Some(row) => integer(&row[0]),
+            None => Ok(0),
+        }
+    }
+
+    /// Sets the schema version of the database.
+    fn set_version(&self, version: i64) -> Result<()> {
+        self.execute(&format!("PRAGMA user_version = {};", version))
+    }
+
+    /// Applies the migrations the database is missing, so its schema matches the tables.sql file.
+    pub fn migrate(&self) -> Result<()> {
+        let version = self.version()?;
+        for (number, migration) in schema::MIGRATIONS.iter().enumerate() {
+            if (number as i64) < version {
+                continue;
+            }
+            info!(target: "MusicDatabase", "Migrating database to version {}", number + 1);
+            self.execute(migration)?;
+            self.set_version(number as i64 + 1)?;
         }
         Ok(())
     }
 
     /// Returns the active connection, if exists.
-    pub fn connection(&self) -> Result<&sqlite::Connection, SQLiteError> {
+    pub fn connection(&self) -> Result<&sqlite::Connection> {
         match self.connection {
             Some(ref connection) => Ok(connection),
-            None => {
-                Err(SQLiteError {
-                    code: Some(1),
-                    message: Some(String::from("Not connected to database"))
-                })
-            }
+            None => Err(Error::NotConnected),
         }
     }
 
     /// Executes an SQL statement.
-    pub fn execute(&self, query: &str) -> Result<(), SQLiteError> {
+    pub fn execute(&self, query: &str) -> Result<()> {
         let connection = self.connection()?;
-        connection.execute(query)
+        connection.execute(query)?;
+        Ok(())
     }
 
     /// Executes an SQL statement, and returns the resulting rows.
-    pub fn query(&self, query: &str) -> Result<sqlite::Cursor, SQLiteError> {
+    pub fn query(&self, query: &str) -> Result<Rows<'_>> {
         let connection = self.connection()?;
-        Ok(connection.prepare(query)?.cursor())
+        Ok(Rows { statement: connection.prepare(query)?, values: Vec::new(), done: false })
     }
 
-    /// Returns all the songs in database, following the order: title, performer, album and genre.
-    pub fn songs(&self) -> Vec<HashMap<&str, String>> {
-        let mut query = query_manager::select(
-            &[Rolas("title"), Rolas("genre"), Performers("name"), Albums("name")],
-            &[Eq(Rolas("id_performer"), Performers("id_performer")), Eq(Rolas("id_album"),
-                Albums("id_album"))]
-        );
-        query += " ORDER BY rolas.title ASC";
-        let mut cursor = self.query(&query).unwrap();
-        let mut songs = Vec::new();
-        while let Some(row) = cursor.next().unwrap() {
-            let mut hashmap: HashMap<&str, String> = HashMap::new();
-            let title = row[0].as_string().unwrap();
-            let genre = row[1].as_string().unwrap();
-            let performer = row[2].as_string().unwrap();
-            let album = row[3].as_string().unwrap();
-            hashmap.insert("title", title.to_owned());
-            hashmap.insert("performer", performer.to_owned());
-            hashmap.insert("album", album.to_owned());
-            hashmap.insert("genre", genre.to_owned());
-            songs.push(hashmap);
-        }
-        songs
-    }
-
-    /// Given an album path, creates a new album entry in the database.
-    pub fn save_album(&mut self, album: path::PathBuf) -> Result<(), SQLiteError> {
-        if self.album_in_database(&album) {
-            return Ok(());
+    /// Executes a statement created by the query manager, binding its parameters, and returns the
+    /// resulting rows.
+    pub fn fetch(&self, query: &Query) -> Result<Rows<'_>> {
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(query.sql())?;
+        for (i, value) in query.parameters().iter().enumerate() {
+            statement.bind(i + 1, value)?;
         }
-        let album_path = album.to_str().unwrap().to_string();
-        let album_name = String::from(album.file_name().unwrap().to_str().unwrap());
-        let query = format!("INSERT INTO albums (path, name, year) VALUES ('{}', '{}', 2018);",
-            album_path, album_name);
-        info!(target: "MusicDatabase", "Inserting album {:?}", album.file_name().unwrap());
-        self.execute(&query)?;
+        Ok(Rows { statement: statement, values: Vec::new(), done: false })
+    }
+
+    /// Executes a statement created by the query manager that does not return rows, such as an
+    /// INSERT, UPDATE or DELETE statement.
+    pub fn run(&self, query: &Query) -> Result<()> {
+        let mut cursor = self.fetch(query)?;
+        while let Some(_) = cursor.next()? {}
         Ok(())
     }
 
-    /// Given a music file, creates a new performer entry in the database.
-    fn save_performer(&self, song: &MusicFile) -> Result<(), SQLiteError>{
-        let performer = match song.artist() {
-            Some(performer) => performer,
-            None => "Unknown",
+    /// Runs a function in a transaction: its changes are kept if it succeeds, and undone if it
+    /// fails. Transactions can be nested, as they are savepoints.
+    pub fn transaction<T, F>(&self, function: F) -> Result<T>
+        where F: FnOnce(&MusicDatabase) -> Result<T> {
+        self.execute("SAVEPOINT music_database;")?;
+        match function(self) {
+            Ok(value) => {
+                self.execute("RELEASE music_database;")?;
+                Ok(value)
+            },
+            Err(e) => {
+                self.execute("ROLLBACK TO music_database; RELEASE music_database;")?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Returns the id of the last row inserted with this connection.
+    pub fn last_insert_id(&self) -> Result<i64> {
+        let mut cursor = self.query("SELECT last_insert_rowid();")?;
+        match cursor.next()? {
+            Some(row) => integer(&row[0]),
+            None => Err(Error::Parse(String::from("No row id after insertion"))),
+        }
+    }
+
+    /// Returns all the songs in database, ordered by title.
+    pub fn songs(&self) -> Result<Vec<Song>> {
+        let query = songs_select()
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Title))))
+            .build();
+        self.songs_from(&query)
+    }
+
+    /// Returns the songs resulting from a query whose columns are `SONG_COLUMNS`.
+    pub fn songs_from(&self, query: &Query) -> Result<Vec<Song>> {
+        let mut cursor = self.fetch(query)?;
+        let mut songs = Vec::new();
+        while let Some(row) = cursor.next()? {
+            songs.push(song_from_row(row)?);
+        }
+        Ok(songs)
+    }
+
+    /// Returns the song with the given id, if exists.
+    pub fn song(&self, id_rola: i64) -> Result<Option<Song>> {
+        let query = songs_select()
+            .filter(EqVal(Rolas(rolas::IdRola), Value::Integer(id_rola)))
+            .build();
+        Ok(self.songs_from(&query)?.pop())
+    }
+
+    /// Returns the number of distinct values of a column of the songs that match a conditional.
+    fn count_songs(&self, column: TC, conditional: Conditional) -> Result<i64> {
+        let query = Select::fields(vec![Field::CountDistinct(column)]).filter(conditional).build();
+        let mut cursor = self.fetch(&query)?;
+        match cursor.next()? {
+            Some(row) => integer(&row[0]),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns the statistics of the library. Only the listed songs, which are not hidden or
+    /// missing, are counted; the hidden and the missing songs are counted apart. The size, the
+    /// formats and the cover art are read from the files, so this may take a while: a file
+    /// removed since the last mining adds nothing to the size, and its song counts as a song
+    /// without art. A song misses tags if it has no title, performer, genre or year.
+    pub fn statistics(&self) -> Result<LibraryStatistics> {
+        let mut statistics = LibraryStatistics {
+            songs: self.count_songs(Rolas(rolas::IdRola), visible())?,
+            albums: self.count_songs(Rolas(rolas::IdAlbum), visible())?,
+            performers: self.count_songs(Rolas(rolas::IdPerformer), visible())?,
+            genres: self.count_songs(Rolas(rolas::Genre), visible())?,
+            hidden: self.count_songs(Rolas(rolas::IdRola),
+                EqVal(Rolas(rolas::Hidden), Value::Integer(1)))?,
+            missing: self.count_songs(Rolas(rolas::IdRola), Conditional::And(vec![
+                EqVal(Rolas(rolas::Hidden), Value::Integer(0)),
+                Conditional::Not(Box::new(IsNull(Rolas(rolas::MissingSince)))),
+            ]))?,
+            ..LibraryStatistics::default()
+        };
+
+        let query = Select::fields(vec![Field::Sum(Rolas(rolas::Duration))])
+            .filter(visible())
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        if let Some(row) = cursor.next()? {
+            statistics.duration = Duration::from_millis(row[0].as_integer().unwrap_or(0) as u64);
+        }
+
+        let mut formats: BTreeMap<String, i64> = BTreeMap::new();
+        let mut cursor = self.fetch(&Select::new(&[Rolas(rolas::Path)]).filter(visible()).build())?;
+        while let Some(row) = cursor.next()? {
+            let path = path::PathBuf::from(text(&row[0]).unwrap_or_default());
+            statistics.size += fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+            let format = path.extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            *formats.entry(format).or_insert(0) += 1;
+        }
+        statistics.formats = formats.into_iter().collect();
+        statistics.formats.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let unknown = |column| EqVal(column, Value::String(String::from(UNKNOWN)));
+        let query = Select::fields(vec![Field::Count(Rolas(rolas::IdRola))])
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .filter(Conditional::Or(vec![
+                unknown(Rolas(rolas::Title)),
+                unknown(Performers(performers::Name)),
+                unknown(Rolas(rolas::Genre)),
+                IsNull(Rolas(rolas::Year)),
+            ]))
+            .filter(visible())
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        if let Some(row) = cursor.next()? {
+            statistics.missing_tags = integer(&row[0])?;
+        }
+
+        for entry in self.album_entries(&SongFilter::default())? {
+            let has_art = entry.album.path.as_ref().and_then(|path| cover_file(path)).is_some()
+                || entry.song_path.as_ref().and_then(|path| embedded_cover(path)).is_some();
+            if !has_art {
+                statistics.missing_art += entry.songs;
+            }
+        }
+
+        let query = Select::fields(vec![Field::Column(Rolas(rolas::Year)),
+                Field::Count(Rolas(rolas::IdRola))])
+            .filter(Conditional::Not(Box::new(IsNull(Rolas(rolas::Year)))))
+            .filter(visible())
+            .group_by(Rolas(rolas::Year))
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Year))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        while let Some(row) = cursor.next()? {
+            statistics.years.push((integer(&row[0])? as i32, integer(&row[1])?));
+        }
+        Ok(statistics)
+    }
+
+    /// Returns all the albums in database, ordered by name.
+    pub fn albums(&self) -> Result<Vec<Album>> {
+        let query = Select::new(&ALBUM_COLUMNS)
+            .order_by(Order::Asc(Field::Column(Albums(albums::Name))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut albums = Vec::new();
+        while let Some(row) = cursor.next()? {
+            albums.push(album_from_row(row)?);
+        }
+        Ok(albums)
+    }
+
+    /// Returns all the performers in database, ordered by name.
+    pub fn performers(&self) -> Result<Vec<Performer>> {
+        let query = Select::new(&PERFORMER_COLUMNS)
+            .order_by(Order::Asc(Field::Column(Performers(performers::Name))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut performers = Vec::new();
+        while let Some(row) = cursor.next()? {
+            performers.push(performer_from_row(row)?);
+        }
+        Ok(performers)
+    }
+
+    /// Returns all the persons in database.
+    pub fn persons(&self) -> Result<Vec<Person>> {
+        let query = query_manager::select(
+            &[Persons(persons::IdPerson), Persons(persons::StageName), Persons(persons::RealName),
+                Persons(persons::BirthDate), Persons(persons::DeathDate)],
+            &[]
+        );
+        let mut cursor = self.fetch(&query)?;
+        let mut persons = Vec::new();
+        while let Some(row) = cursor.next()? {
+            persons.push(Person {
+                id: integer(&row[0])?,
+                stage_name: text(&row[1]),
+                real_name: text(&row[2]),
+                birth_date: text(&row[3]),
+                death_date: text(&row[4]),
+            });
+        }
+        Ok(persons)
+    }
+
+    /// Returns all the groups in database.
+    pub fn groups(&self) -> Result<Vec<Group>> {
+        let query = query_manager::select(
+            &[Groups(groups::IdGroup), Groups(groups::Name), Groups(groups::StartDate), Groups(groups::EndDate)],
+            &[]
+        );
+        let mut cursor = self.fetch(&query)?;
+        let mut groups = Vec::new();
+        while let Some(row) = cursor.next()? {
+            groups.push(Group {
+                id: integer(&row[0])?,
+                name: text(&row[1]).unwrap_or_default(),
+                start_date: text(&row[2]),
+                end_date: text(&row[3]),
+            });
+        }
+        Ok(groups)
+    }
+
+    /// Given a music file, returns the id of its album, creating a new album entry in the
+    /// database if it does not exist yet. Albums are identified by their album artist, name and
+    /// directory; if the album has no year yet, it takes the music file's year.
+    pub fn save_album(&self, song: &MusicFile) -> Result<i64> {
+        let directory = song.album_directory();
+        let album_path = directory.to_string_lossy().into_owned();
+        let album_name = match song.album() {
+            Some(album) => album.to_owned(),
+            None => match directory.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => String::from(UNKNOWN),
+            },
         };
-        let query = format!("INSERT INTO performers (id_type, name) VALUES (2, '{}');",
-            performer);
+        let album_artist = match (song.album_artist(), song.artist()) {
+            (Some(album_artist), _) => album_artist.as_str(),
+            (None, Some(artist)) => artist.as_str(),
+            (None, None) => UNKNOWN,
+        };
+        let id_performer = self.save_performer(album_artist)?;
+        let year = song.year().map(|year| year as i64);
+
+        let query = query_manager::select(
+            &[Albums(albums::IdAlbum), Albums(albums::Year)],
+            &[EqVal(Albums(albums::Name), Value::String(album_name.clone())),
+                EqVal(Albums(albums::IdPerformer), Value::Integer(id_performer)),
+                EqVal(Albums(albums::Path), Value::String(album_path.clone()))]
+        );
+        let mut cursor = self.fetch(&query)?;
+        if let Some(row) = cursor.next()? {
+            let id_album = integer(&row[0])?;
+            if row[1].as_integer().is_none() && year.is_some() {
+                let query = Update::new()
+                    .set(Albums(albums::Year), optional_integer(year))
+                    .filter(EqVal(Albums(albums::IdAlbum), Value::Integer(id_album)))
+                    .build();
+                self.run(&query)?;
+            }
+            return Ok(id_album);
+        }
+        let query = Insert::new()
+            .value(Albums(albums::Path), Value::String(album_path))
+            .value(Albums(albums::Name), Value::String(album_name.clone()))
+            .value(Albums(albums::Year), optional_integer(year))
+            .value(Albums(albums::IdPerformer), Value::Integer(id_performer))
+            .build();
+        info!(target: "MusicDatabase", "Inserting album {:?}", album_name);
+        self.run(&query)?;
+        self.last_insert_id()
+    }
+
+    /// Deletes the albums without songs, such as the albums of songs whose album changed.
+    pub fn delete_empty_albums(&self) -> Result<()> {
+        info!(target: "MusicDatabase", "Deleting albums without songs");
+        self.execute("DELETE FROM albums WHERE id_album NOT IN \
+            (SELECT id_album FROM rolas WHERE id_album IS NOT NULL);")
+    }
+
+    /// Returns the genres of the visible songs, with their number of songs, ordered by genre.
+    pub fn genre_counts(&self) -> Result<Vec<(String, i64)>> {
+        let query = Select::fields(vec![Field::Column(Rolas(rolas::Genre)),
+                Field::Count(Rolas(rolas::IdRola))])
+            .filter(visible())
+            .group_by(Rolas(rolas::Genre))
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Genre))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut genres = Vec::new();
+        while let Some(row) = cursor.next()? {
+            genres.push((text(&row[0]).unwrap_or_default(), integer(&row[1])?));
+        }
+        Ok(genres)
+    }
+
+    /// Returns the performers of the songs of the filter's genre, with their number of songs in
+    /// that genre, ordered by name. The filter's performer and album are ignored.
+    pub fn performer_counts(&self, filter: &SongFilter) -> Result<Vec<(Performer, i64)>> {
+        let filter = SongFilter { genre: filter.genre.clone(), ..SongFilter::default() };
+        let mut fields: Vec<Field> = PERFORMER_COLUMNS.iter()
+            .map(|column| Field::Column(*column))
+            .collect();
+        fields.push(Field::Count(Rolas(rolas::IdRola)));
+        let mut query = Select::fields(fields)
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .group_by(Performers(performers::IdPerformer))
+            .order_by(Order::Asc(Field::Column(Performers(performers::Name))));
+        for conditional in filter.conditionals() {
+            query = query.filter(conditional);
+        }
+        let mut cursor = self.fetch(&query.build())?;
+        let mut performers = Vec::new();
+        while let Some(row) = cursor.next()? {
+            performers.push((performer_from_row(row)?, integer(&row[PERFORMER_COLUMNS.len()])?));
+        }
+        Ok(performers)
+    }
+
+    /// Returns the albums of the songs of the filter's genre and performer, with their number of
+    /// songs that match them, ordered by name. The filter's album is ignored.
+    pub fn album_counts(&self, filter: &SongFilter) -> Result<Vec<(Album, i64)>> {
+        Ok(self.album_entries(filter)?.into_iter()
+            .map(|entry| (entry.album, entry.songs))
+            .collect())
+    }
+
+    /// Returns the albums of the songs of the filter's genre and performer, with their
+    /// performers, their number of songs that match them and the path of one of those songs,
+    /// ordered by name. The filter's album is ignored.
+    pub fn album_entries(&self, filter: &SongFilter) -> Result<Vec<AlbumEntry>> {
+        let filter = SongFilter { id_album: None, ..filter.clone() };
+        let mut fields: Vec<Field> = ALBUM_COLUMNS.iter()
+            .map(|column| Field::Column(*column))
+            .collect();
+        fields.push(Field::Column(Performers(performers::Name)));
+        fields.push(Field::Count(Rolas(rolas::IdRola)));
+        fields.push(Field::Min(Rolas(rolas::Path)));
+        let mut query = Select::fields(fields)
+            .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)))
+            .join(Join::Left(Albums(albums::IdPerformer), Performers(performers::IdPerformer)))
+            .group_by(Albums(albums::IdAlbum))
+            .order_by(Order::Asc(Field::Column(Albums(albums::Name))));
+        for conditional in filter.conditionals() {
+            query = query.filter(conditional);
+        }
+        let mut cursor = self.fetch(&query.build())?;
+        let mut entries = Vec::new();
+        while let Some(row) = cursor.next()? {
+            let columns = ALBUM_COLUMNS.len();
+            entries.push(AlbumEntry {
+                album: album_from_row(row)?,
+                performer: text(&row[columns]),
+                songs: integer(&row[columns + 1])?,
+                song_path: text(&row[columns + 2]).map(path::PathBuf::from),
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Returns the ids of the songs that match a filter.
+    pub fn song_ids(&self, filter: &SongFilter) -> Result<Vec<i64>> {
+        let mut query = Select::new(&[Rolas(rolas::IdRola)]);
+        for conditional in filter.conditionals() {
+            query = query.filter(conditional);
+        }
+        let mut cursor = self.fetch(&query.build())?;
+        let mut ids = Vec::new();
+        while let Some(row) = cursor.next()? {
+            ids.push(integer(&row[0])?);
+        }
+        Ok(ids)
+    }
+
+    /// Returns the distinct values of a text column that start with a prefix, in any case, in
+    /// alphabetical order, at most `limit`.
+    pub fn values_starting_with(&self, column: TC, prefix: &str, limit: i64)
+        -> Result<Vec<String>> {
+        let query = Select::new(&[column])
+            .distinct()
+            .filter(StartsWith(column, prefix.to_owned()))
+            .order_by(Order::Asc(Field::Column(column)))
+            .limit(limit)
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut values = Vec::new();
+        while let Some(row) = cursor.next()? {
+            values.extend(text(&row[0]));
+        }
+        Ok(values)
+    }
+
+    /// Returns the songs of an album, ordered by disc and track number.
+    pub fn album_songs(&self, id_album: i64) -> Result<Vec<Song>> {
+        let query = songs_select()
+            .filter(EqVal(Albums(albums::IdAlbum), Value::Integer(id_album)))
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Disc))))
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Track))))
+            .build();
+        self.songs_from(&query)
+    }
+
+    /// Given a performer name, returns its id, creating a new performer entry in the database if
+    /// it does not exist yet.
+    pub fn save_performer(&self, performer: &str) -> Result<i64> {
+        let query = query_manager::select(
+            &[Performers(performers::IdPerformer)],
+            &[EqVal(Performers(performers::Name), Value::String(performer.to_owned()))]
+        );
+        let mut cursor = self.fetch(&query)?;
+        if let Some(row) = cursor.next()? {
+            return integer(&row[0]);
+        }
+        let query = Insert::new()
+            .value(Performers(performers::IdType), Value::Integer(PerformerType::Unknown.id()))
+            .value(Performers(performers::Name), Value::String(performer.to_owned()))
+            .build();
         info!(target: "MusicDatabase", "Inserting performer {:?}", performer);
-        self.execute(&query)?;
-        Ok(())
+        self.run(&query)?;
+        self.last_insert_id()
     }
 
     /// Given a music file, creates a new "rolas" entry in the database, storing it performer
-    /// and album.
-    pub fn save_song(&self, song: MusicFile) -> Result<(), SQLiteError> {
-        if self.song_in_database(&song) {
-            return Ok(());
-        }
-        self.save_performer(&song)?;
-        let values = self.song_as_values(&song);
-        let title = match song.title() {
-            Some(title) => title,
-            None => "",
+    /// and album. Songs are identified by their path: if the song is already stored, its entry
+    /// is updated when the file's tag changed.
+    /// A missing song whose file is saved again is no longer missing.
+    pub fn save_song(&self, song: MusicFile) -> Result<SongChange> {
+        let values = self.song_values(&song)?;
+        let id_rola = match self.song_id(&song.path())? {
+            Some(id_rola) => id_rola,
+            None => {
+                let mut insert = Insert::new()
+                    .value(Rolas(rolas::DateAdded), Value::Integer(now()));
+                for (column, value) in values {
+                    insert = insert.value(column, value);
+                }
+                info!(target: "MusicDatabase", "Inserting song {:?}", song.path());
+                self.run(&insert.build())?;
+                return Ok(SongChange::Added(self.last_insert_id()?));
+            },
         };
-        let query = format!("INSERT INTO rolas (id_performer, id_album, path, title, track, year, \
-        genre) VALUES {};", values);
-        info!(target: "MusicDatabase", "Inserting song {}", title);
-        self.execute(&query)?;
-        Ok(())
+        let id = EqVal(Rolas(rolas::IdRola), Value::Integer(id_rola));
+        let mut stored = Select::new(&[Rolas(rolas::IdRola)]).filter(id.clone());
+        let mut update = Update::new().filter(id);
+        for (column, value) in values {
+            stored = stored.filter(match value {
+                Value::Null => IsNull(column),
+                ref value => EqVal(column, value.clone()),
+            });
+            update = update.set(column, value);
+        }
+        if self.fetch(&stored.build())?.next()?.is_some() {
+            return Ok(SongChange::Unchanged(id_rola));
+        }
+        info!(target: "MusicDatabase", "Updating song {:?}", song.path());
+        self.run(&update.build())?;
+        Ok(SongChange::Updated(id_rola))
+    }
+
+    /// Given a music file, returns the statement that inserts it in the "rolas" table.
+    pub fn song_as_insert(&self, song: &MusicFile) -> Result<Query> {
+        let mut insert = Insert::new();
+        for (column, value) in self.song_values(song)? {
+            insert = insert.value(column, value);
+        }
+        Ok(insert.build())
     }
 
-    /// Given a music file, returns a string with all the values to be inserted in the database.
-    pub fn song_as_values(&self, song: &MusicFile) -> String {
+    /// Given a music file, returns the values of its "rolas" entry, storing its performer and
+    /// album if they do not exist yet.
+    fn song_values(&self, song: &MusicFile) -> Result<Vec<(TC, Value)>> {
         let performer = match song.artist() {
             Some(performer) => performer,
-            None => "Unknown",
+            None => UNKNOWN,
         };
-        let id_performer = self.foreign_key("performer", "name", &performer);
+        let id_performer = self.save_performer(&performer)?;
 
-        let album = match song.album() {
-            Some(album) => album,
-            None => "Unknown",
-        };
-        let id_album = self.foreign_key("album", "name", &album);
+        let id_album = self.save_album(song)?;
 
-        let path = song.path();
         let title = match song.title() {
             Some(title) => title,
-            None => "Unknown",
-        };
-        let track = match song.track() {
-            Some(track) => track,
-            None => &0,
-        };
-        let date_recorded = match song.date_recorded() {
-            Some(date_recorded) => date_recorded,
-            None => &Timestamp{ year: 2018, month: None, day: None, hour: None, minute: None,
-                second: None },
+            None => UNKNOWN,
         };
         let genre = match song.genre() {
             Some(genre) => genre,
-            None => "Unknown",
+            None => UNKNOWN,
         };
-        format!("({}, {}, '{}', '{}', {}, {}, '{}')", id_performer, id_album, path, title, track,
-        date_recorded.to_string(), genre)
+        Ok(vec![
+            (Rolas(rolas::IdPerformer), Value::Integer(id_performer)),
+            (Rolas(rolas::IdAlbum), Value::Integer(id_album)),
+            (Rolas(rolas::Path), Value::String(song.path())),
+            (Rolas(rolas::Title), Value::String(title.to_owned())),
+            (Rolas(rolas::Track), Value::Integer(song.track().unwrap_or(0) as i64)),
+            (Rolas(rolas::Year), optional_integer(song.year().map(|year| year as i64))),
+            (Rolas(rolas::Genre), Value::String(genre.to_owned())),
+            (Rolas(rolas::Duration), optional_integer(song.duration().map(|duration| duration as i64))),
+            (Rolas(rolas::Disc), optional_integer(song.disc().map(|disc| disc as i64))),
+            (Rolas(rolas::AudioHash),
+                song.audio_hash().clone().map_or(Value::Null, Value::String)),
+            (Rolas(rolas::MissingSince), Value::Null),
+            (Rolas(rolas::Modified), optional_integer(*song.modified())),
+            (Rolas(rolas::Bitrate), optional_integer(song.bitrate().map(|bitrate| bitrate as i64))),
+        ])
+    }
+
+    /// Sets the rating of a song, from 0 (unrated) to `MAX_RATING`.
+    pub fn set_rating(&self, id_rola: i64, rating: u8) -> Result<()> {
+        if rating > MAX_RATING {
+            return Err(Error::InvalidRating(rating));
+        }
+        self.existing_song(id_rola)?;
+        self.update_song(id_rola, vec![(Rolas(rolas::Rating), Value::Integer(rating as i64))])
     }
 
-    /// Given the table, column and value, returns the corresponding row id.
-    /// If the value does not exists in database, it is inserted.
-    pub fn foreign_key(&self, table: &str, column: &str, column_value: &str) -> i64 {
-        let column_query = format!("id_{}", table);
-        let select_table = TC::from_str(table, &column_query).unwrap();
-        let where_table_column = TC::from_str(table, column).unwrap();
-        let conditional = EqVal(where_table_column, column_value);
+    /// Records that a song was played: its play count is incremented and it is last played
+    /// now.
+    pub fn record_play(&self, id_rola: i64) -> Result<()> {
+        self.existing_song(id_rola)?;
+        let query = Update::new()
+            .set(Rolas(rolas::LastPlayed), Value::Integer(now()))
+            .increment(Rolas(rolas::PlayCount))
+            .filter(EqVal(Rolas(rolas::IdRola), Value::Integer(id_rola)))
+            .build();
+        self.run(&query)
+    }
+
+    /// Records that a song was skipped: its skip count is incremented.
+    pub fn record_skip(&self, id_rola: i64) -> Result<()> {
+        self.existing_song(id_rola)?;
+        let query = Update::new()
+            .increment(Rolas(rolas::SkipCount))
+            .filter(EqVal(Rolas(rolas::IdRola), Value::Integer(id_rola)))
+            .build();
+        self.run(&query)
+    }
+
+    /// Hides a song, or shows it again. A hidden song stays in the database, so it is not added
+    /// again when the library is mined, but it is not listed, browsed, searched or reported as a
+    /// duplicate.
+    pub fn set_hidden(&self, id_rola: i64, hidden: bool) -> Result<()> {
+        self.update_song(id_rola, vec![(Rolas(rolas::Hidden), Value::Integer(hidden as i64))])
+    }
+
+    /// Merges songs into a song that is kept: its play and skip counts become the sums of
+    /// theirs, its rating the highest one, its last play the latest one and its date added the
+    /// earliest one, and their playbacks become its playbacks. The merged songs are deleted.
+    /// The songs are merged in a transaction, so they are all merged or none is.
+    pub fn merge_songs(&self, id_kept: i64, ids: &[i64]) -> Result<()> {
+        self.transaction(|database| database.merge_into(id_kept, ids))
+    }
 
-        let query = query_manager::select(&[select_table], &[conditional]);
-        let mut cursor = self.query(&query).unwrap();
-        if let Some(row) = cursor.next().unwrap() {
-            row[0].as_integer().unwrap()
+    /// Merges songs into a song that is kept, as `merge_songs`, outside of a transaction.
+    fn merge_into(&self, id_kept: i64, ids: &[i64]) -> Result<()> {
+        let mut all = vec![id_kept];
+        all.extend(ids.iter().filter(|id| **id != id_kept));
+        let query = Select::new(&[Rolas(rolas::Rating), Rolas(rolas::PlayCount),
+                Rolas(rolas::SkipCount), Rolas(rolas::LastPlayed), Rolas(rolas::DateAdded)])
+            .filter(In(Rolas(rolas::IdRola), all.iter().map(|id| Value::Integer(*id)).collect()))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let (mut found, mut rating, mut play_count, mut skip_count) = (0, 0, 0, 0);
+        let (mut last_played, mut date_added): (Option<i64>, Option<i64>) = (None, None);
+        while let Some(row) = cursor.next()? {
+            found += 1;
+            rating = rating.max(row[0].as_integer().unwrap_or(0));
+            play_count += row[1].as_integer().unwrap_or(0);
+            skip_count += row[2].as_integer().unwrap_or(0);
+            last_played = last_played.max(row[3].as_integer());
+            date_added = match (date_added, row[4].as_integer()) {
+                (Some(date), Some(other)) => Some(date.min(other)),
+                (date, other) => date.or(other),
+            };
         }
-        else {
-            self.insert_and_get_id(table, column, column_value)
+        if found != all.len() {
+            return Err(Error::NotFound(format!("songs in {:?}", all)));
+        }
+        self.update_song(id_kept, vec![
+            (Rolas(rolas::Rating), Value::Integer(rating)),
+            (Rolas(rolas::PlayCount), Value::Integer(play_count)),
+            (Rolas(rolas::SkipCount), Value::Integer(skip_count)),
+            (Rolas(rolas::LastPlayed), optional_integer(last_played)),
+            (Rolas(rolas::DateAdded), optional_integer(date_added)),
+        ])?;
+        for id_rola in &all[1..] {
+            let query = Update::new()
+                .set(History(history::IdRola), Value::Integer(id_kept))
+                .filter(EqVal(History(history::IdRola), Value::Integer(*id_rola)))
+                .build();
+            self.run(&query)?;
+            self.delete_song(*id_rola)?;
         }
+        Ok(())
     }
 
-    /// Given the table, column and value, creates the corresponding new entry in the database.
-    pub fn insert_and_get_id(&self, table: &str, column: &str, column_value: &str) -> i64 {
-        let query = format!("INSERT INTO {}s ({}) VALUES ('{}');", table, column, column_value);
-        self.execute(&query).unwrap();
-        let query = format!("SELECT last_insert_rowid();");
-        let mut cursor = self.query(&query).unwrap();
-        if let Some(row) = cursor.next().unwrap() {
-            row[0].as_integer().unwrap()
+    /// Returns the groups of songs that are likely copies of the same recording: the songs with
+    /// the same audio hash, and the songs with the same performer and title, ignoring case and
+    /// punctuation, whose durations differ by at most `tolerance`. A group by metadata is not
+    /// reported if its songs already are a group by audio. Hidden songs are ignored.
+    pub fn duplicates(&self, tolerance: Duration) -> Result<Vec<DuplicateGroup>> {
+        let mut hashes: HashMap<i64, String> = HashMap::new();
+        let mut by_audio: BTreeMap<String, Vec<Song>> = BTreeMap::new();
+        let mut by_metadata: BTreeMap<(String, String), Vec<Song>> = BTreeMap::new();
+        for (song, audio_hash) in self.songs_with_hashes(visible())? {
+            if let Some(hash) = audio_hash {
+                hashes.insert(song.id, hash.clone());
+                by_audio.entry(hash).or_insert_with(Vec::new).push(song.clone());
+            }
+            if let Some(key) = metadata_key(&song) {
+                by_metadata.entry(key).or_insert_with(Vec::new).push(song);
+            }
         }
-        else {
-            0
+
+        let mut groups: Vec<DuplicateGroup> = by_audio.into_iter()
+            .filter(|&(_, ref songs)| songs.len() > 1)
+            .map(|(_, songs)| DuplicateGroup { reason: DuplicateReason::SameAudio, songs: songs })
+            .collect();
+        let same_audio = |songs: &[Song]| {
+            let hash = hashes.get(&songs[0].id);
+            hash.is_some() && songs.iter().all(|song| hashes.get(&song.id) == hash)
+        };
+        for (_, mut songs) in by_metadata {
+            songs.sort_by_key(|song| song.duration);
+            let mut close_songs: Vec<Vec<Song>> = Vec::new();
+            for song in songs {
+                let is_close = close_songs.last()
+                    .and_then(|close| close.last())
+                    .map_or(false, |last| {
+                        song.duration.unwrap_or_default() - last.duration.unwrap_or_default()
+                            <= tolerance
+                    });
+                if !is_close {
+                    close_songs.push(Vec::new());
+                }
+                if let Some(close) = close_songs.last_mut() {
+                    close.push(song);
+                }
+            }
+            groups.extend(close_songs.into_iter()
+                .filter(|songs| songs.len() > 1 && !same_audio(songs))
+                .map(|songs| DuplicateGroup {
+                    reason: DuplicateReason::SameMetadata,
+                    songs: songs,
+                }));
         }
+        groups.sort_by(|a, b| a.songs[0].title.cmp(&b.songs[0].title));
+        Ok(groups)
     }
 
-    /// Checks if the given song exists in database.
-    pub fn song_in_database(&self, song: &MusicFile) -> bool {
-        let title = match song.title() {
-            Some(title) => title,
-            None => "Unknown",
+    /// Finds, among some songs, the song that another one was moved to: the song with the same
+    /// audio hash or, if there is none, the song with the same performer, title and album,
+    /// ignoring case and punctuation, whose duration differs by at most `DUPLICATE_TOLERANCE`
+    /// seconds.
+    pub fn find_moved(&self, id_rola: i64, candidates: &[i64]) -> Result<Option<i64>> {
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let mut ids: Vec<Value> = candidates.iter().map(|id| Value::Integer(*id)).collect();
+        ids.push(Value::Integer(id_rola));
+        let mut songs = self.songs_with_hashes(In(Rolas(rolas::IdRola), ids))?;
+        let position = songs.iter().position(|&(ref song, _)| song.id == id_rola);
+        let (moved, audio_hash) = match position {
+            Some(position) => songs.remove(position),
+            None => return Ok(None),
+        };
+        if audio_hash.is_some() {
+            if let Some(&(ref song, _)) = songs.iter().find(|&&(_, ref hash)| *hash == audio_hash) {
+                return Ok(Some(song.id));
+            }
+        }
+        let key = match metadata_key(&moved) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let tolerance = Duration::from_secs(DUPLICATE_TOLERANCE);
+        let is_close = |song: &Song| match (song.duration, moved.duration) {
+            (Some(duration), Some(moved_duration)) if duration > moved_duration => {
+                duration - moved_duration <= tolerance
+            },
+            (Some(duration), Some(moved_duration)) => moved_duration - duration <= tolerance,
+            _ => false,
+        };
+        let found = songs.iter().map(|&(ref song, _)| song).find(|song| {
+            metadata_key(song).as_ref() == Some(&key) && is_close(song)
+                && normalize(&song.album.name) == normalize(&moved.album.name)
+        });
+        Ok(found.map(|song| song.id))
+    }
+
+    /// Moves a song to another music file: the file is stored as a song, which is then moved
+    /// into the song with `move_song`, so the song keeps its id, rating, counts and playbacks.
+    pub fn relocate_song(&self, id_rola: i64, file: MusicFile) -> Result<()> {
+        let id_file = match self.save_song(file)? {
+            SongChange::Added(id) | SongChange::Updated(id) | SongChange::Unchanged(id) => id,
         };
+        if id_file != id_rola {
+            self.move_song(id_rola, id_file)?;
+        }
+        Ok(())
+    }
+
+    /// Moves a song to the file of another stored song: the song takes the path and the tags of
+    /// the other one, which is merged into it and deleted. The song keeps its id and whether it
+    /// is hidden, and it is no longer missing.
+    pub fn move_song(&self, id_rola: i64, id_file: i64) -> Result<()> {
+        let query = Select::new(&FILE_COLUMNS)
+            .filter(EqVal(Rolas(rolas::IdRola), Value::Integer(id_file)))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let values: Vec<(TC, Value)> = match cursor.next()? {
+            Some(row) => FILE_COLUMNS.iter().cloned().zip(row.iter().cloned()).collect(),
+            None => return Err(Error::NotFound(format!("song {}", id_file))),
+        };
+        self.transaction(|database| {
+            database.merge_into(id_rola, &[id_file])?;
+            database.update_song(id_rola, values)
+        })
+    }
+
+    /// Marks a song as missing since now, because its file was not found. A missing song is not
+    /// listed until its file is found again, or it is relocated or deleted.
+    pub fn set_missing(&self, id_rola: i64) -> Result<()> {
+        self.update_song(id_rola, vec![(Rolas(rolas::MissingSince), Value::Integer(now()))])
+    }
+
+    /// Returns the missing songs, with the time since they are missing, ordered by path. Hidden
+    /// songs are not listed even if they are missing.
+    pub fn missing_songs(&self) -> Result<Vec<(Song, i64)>> {
+        let mut songs = Vec::new();
+        for stored in self.stored_songs()? {
+            if let (false, Some(missing_since)) = (stored.hidden, stored.missing_since) {
+                songs.push((stored.song, missing_since));
+            }
+        }
+        Ok(songs)
+    }
+
+    /// Returns every stored song, hidden and missing songs included, ordered by path.
+    pub fn stored_songs(&self) -> Result<Vec<StoredSong>> {
+        let mut columns = SONG_COLUMNS.to_vec();
+        columns.extend_from_slice(&[Rolas(rolas::Hidden), Rolas(rolas::MissingSince)]);
+        let query = Select::new(&columns)
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)))
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Path))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut songs = Vec::new();
+        while let Some(row) = cursor.next()? {
+            songs.push(StoredSong {
+                song: song_from_row(row)?,
+                hidden: row[SONG_COLUMNS.len()].as_integer().unwrap_or(0) != 0,
+                missing_since: row[SONG_COLUMNS.len() + 1].as_integer(),
+            });
+        }
+        Ok(songs)
+    }
+
+    /// Returns the songs that match a conditional, with their audio hash, ordered by path.
+    fn songs_with_hashes(&self, conditional: Conditional) -> Result<Vec<(Song, Option<String>)>> {
+        let mut columns = SONG_COLUMNS.to_vec();
+        columns.push(Rolas(rolas::AudioHash));
+        let query = Select::new(&columns)
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)))
+            .filter(conditional)
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Path))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut songs = Vec::new();
+        while let Some(row) = cursor.next()? {
+            songs.push((song_from_row(row)?, text(&row[SONG_COLUMNS.len()])));
+        }
+        Ok(songs)
+    }
+
+    /// Returns the song with the given id, failing if it is not stored.
+    fn existing_song(&self, id_rola: i64) -> Result<Song> {
+        match self.song(id_rola)? {
+            Some(song) => Ok(song),
+            None => Err(Error::NotFound(format!("song {}", id_rola))),
+        }
+    }
+
+    /// Sets values of the entry of a song.
+    fn update_song(&self, id_rola: i64, values: Vec<(TC, Value)>) -> Result<()> {
+        let mut update = Update::new()
+            .filter(EqVal(Rolas(rolas::IdRola), Value::Integer(id_rola)));
+        for (column, value) in values {
+            update = update.set(column, value);
+        }
+        self.run(&update.build())
+    }
+
+    /// Stores a playback of a song in the listening history: when it started, for how long the
+    /// song was listened and whether it was listened until its end. A completed playback counts
+    /// as a play of the song, and an incomplete one as a skip. Returns the id of the playback.
+    pub fn record_playback(&self, id_rola: i64, started: i64, listened: Duration,
+        completed: bool) -> Result<i64> {
+        if completed {
+            self.record_play(id_rola)?;
+        } else {
+            self.record_skip(id_rola)?;
+        }
+        let query = Insert::new()
+            .value(History(history::IdRola), Value::Integer(id_rola))
+            .value(History(history::Started), Value::Integer(started))
+            .value(History(history::Listened), Value::Integer(duration_millis(listened)))
+            .value(History(history::Completed), Value::Integer(completed as i64))
+            .build();
+        self.run(&query)?;
+        self.last_insert_id()
+    }
+
+    /// Returns the playbacks of a period, the latest first, at most `limit` if there is one.
+    pub fn history(&self, period: &Period, limit: Option<i64>) -> Result<Vec<Playback>> {
+        let mut columns = SONG_COLUMNS.to_vec();
+        columns.extend_from_slice(&[History(history::IdHistory), History(history::Started),
+            History(history::Listened), History(history::Completed)]);
+        let mut query = Select::new(&columns)
+            .join(Join::Inner(History(history::IdRola), Rolas(rolas::IdRola)))
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)))
+            .order_by(Order::Desc(Field::Column(History(history::Started))))
+            .order_by(Order::Desc(Field::Column(History(history::IdHistory))));
+        for conditional in period_conditionals(period) {
+            query = query.filter(conditional);
+        }
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        let mut cursor = self.fetch(&query.build())?;
+        let mut playbacks = Vec::new();
+        let song_columns = SONG_COLUMNS.len();
+        while let Some(row) = cursor.next()? {
+            playbacks.push(Playback {
+                song: song_from_row(row)?,
+                id: integer(&row[song_columns])?,
+                started: integer(&row[song_columns + 1])?,
+                listened: Duration::from_millis(row[song_columns + 2].as_integer()
+                    .unwrap_or(0) as u64),
+                completed: row[song_columns + 3].as_integer().unwrap_or(0) != 0,
+            });
+        }
+        Ok(playbacks)
+    }
+
+    /// Returns the most played performers of a period, with their number of playbacks, at most
+    /// `limit` if there is one.
+    pub fn top_performers(&self, period: &Period, limit: Option<i64>)
+        -> Result<Vec<(Performer, i64)>> {
+        let mut fields: Vec<Field> = PERFORMER_COLUMNS.iter()
+            .map(|column| Field::Column(*column))
+            .collect();
+        fields.push(Field::Count(History(history::IdHistory)));
+        let query = Select::fields(fields)
+            .join(Join::Inner(History(history::IdRola), Rolas(rolas::IdRola)))
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .group_by(Performers(performers::IdPerformer));
+        let mut cursor = self.fetch(&top_query(query, period, limit))?;
+        let mut performers = Vec::new();
+        while let Some(row) = cursor.next()? {
+            performers.push((performer_from_row(row)?, integer(&row[PERFORMER_COLUMNS.len()])?));
+        }
+        Ok(performers)
+    }
+
+    /// Returns the most played albums of a period, with their number of playbacks, at most
+    /// `limit` if there is one.
+    pub fn top_albums(&self, period: &Period, limit: Option<i64>) -> Result<Vec<(Album, i64)>> {
+        let mut fields: Vec<Field> = ALBUM_COLUMNS.iter()
+            .map(|column| Field::Column(*column))
+            .collect();
+        fields.push(Field::Count(History(history::IdHistory)));
+        let query = Select::fields(fields)
+            .join(Join::Inner(History(history::IdRola), Rolas(rolas::IdRola)))
+            .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)))
+            .group_by(Albums(albums::IdAlbum));
+        let mut cursor = self.fetch(&top_query(query, period, limit))?;
+        let mut albums = Vec::new();
+        while let Some(row) = cursor.next()? {
+            albums.push((album_from_row(row)?, integer(&row[ALBUM_COLUMNS.len()])?));
+        }
+        Ok(albums)
+    }
+
+    /// Returns the listening of every day of a period with playbacks, in chronological order.
+    /// Days are in UTC.
+    pub fn listening_timeline(&self, period: &Period) -> Result<Vec<DayListening>> {
+        let mut query = Select::new(&[History(history::Started), History(history::Listened)])
+            .order_by(Order::Asc(Field::Column(History(history::Started))));
+        for conditional in period_conditionals(period) {
+            query = query.filter(conditional);
+        }
+        let mut cursor = self.fetch(&query.build())?;
+        let mut timeline: Vec<DayListening> = Vec::new();
+        while let Some(row) = cursor.next()? {
+            let date = format_date(integer(&row[0])?);
+            let listened = Duration::from_millis(row[1].as_integer().unwrap_or(0) as u64);
+            if let Some(day) = timeline.last_mut() {
+                if day.date == date {
+                    day.playbacks += 1;
+                    day.listened += listened;
+                    continue;
+                }
+            }
+            timeline.push(DayListening { date: date, playbacks: 1, listened: listened });
+        }
+        Ok(timeline)
+    }
+
+    /// Returns the smart playlists, ordered by name.
+    pub fn playlists(&self) -> Result<Vec<SmartPlaylist>> {
+        let query = Select::new(&PLAYLIST_COLUMNS)
+            .order_by(Order::Asc(Field::Column(Playlists(playlists::Name))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut playlists = Vec::new();
+        while let Some(row) = cursor.next()? {
+            playlists.push(playlist_from_row(row)?);
+        }
+        Ok(playlists)
+    }
+
+    /// Returns the smart playlist with the given name, if exists.
+    pub fn playlist(&self, name: &str) -> Result<Option<SmartPlaylist>> {
+        let query = query_manager::select(&PLAYLIST_COLUMNS,
+            &[EqVal(Playlists(playlists::Name), Value::String(name.to_owned()))]);
+        let mut cursor = self.fetch(&query)?;
+        match cursor.next()? {
+            Some(row) => Ok(Some(playlist_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores a smart playlist. Playlists are identified by their name: if one with the same
+    /// name is already stored, its rules, order and limit are replaced. Returns the id of the
+    /// playlist.
+    /// The rules are not checked here, `SearchManager::save_playlist` parses them first.
+    pub fn save_playlist(&self, playlist: &SmartPlaylist) -> Result<i64> {
+        let values = vec![
+            (Playlists(playlists::Rules), Value::String(playlist.rules.clone())),
+            (Playlists(playlists::SortOrder), Value::String(playlist.order.name().to_owned())),
+            (Playlists(playlists::MaxSongs), optional_integer(playlist.limit)),
+        ];
+        if let Some(stored) = self.playlist(&playlist.name)? {
+            let mut update = Update::new()
+                .filter(EqVal(Playlists(playlists::IdPlaylist), Value::Integer(stored.id)));
+            for (column, value) in values {
+                update = update.set(column, value);
+            }
+            info!(target: "MusicDatabase", "Updating playlist {:?}", playlist.name);
+            self.run(&update.build())?;
+            return Ok(stored.id);
+        }
+        let mut insert = Insert::new()
+            .value(Playlists(playlists::Name), Value::String(playlist.name.clone()));
+        for (column, value) in values {
+            insert = insert.value(column, value);
+        }
+        info!(target: "MusicDatabase", "Inserting playlist {:?}", playlist.name);
+        self.run(&insert.build())?;
+        self.last_insert_id()
+    }
+
+    /// Removes the smart playlist with the given id from the database.
+    pub fn delete_playlist(&self, id_playlist: i64) -> Result<()> {
+        let query = Delete::from(Table::Playlists)
+            .filter(EqVal(Playlists(playlists::IdPlaylist), Value::Integer(id_playlist)))
+            .build();
+        info!(target: "MusicDatabase", "Deleting playlist {}", id_playlist);
+        self.run(&query)
+    }
+
+    /// Returns the saved searches, ordered by name.
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        let query = Select::new(&SEARCH_COLUMNS)
+            .order_by(Order::Asc(Field::Column(Searches(searches::Name))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut saved_searches = Vec::new();
+        while let Some(row) = cursor.next()? {
+            saved_searches.push(SavedSearch {
+                id: integer(&row[0])?,
+                name: text(&row[1]).unwrap_or_default(),
+                query: text(&row[2]).unwrap_or_default(),
+            });
+        }
+        Ok(saved_searches)
+    }
+
+    /// Saves a search with a name, replacing the query of the search with the same name if there
+    /// is one. Returns the id of the saved search.
+    /// The query is not checked here, `SearchManager::save_search` parses it first.
+    pub fn save_search(&self, name: &str, search: &str) -> Result<i64> {
+        let stored = query_manager::select(&[Searches(searches::IdSearch)],
+            &[EqVal(Searches(searches::Name), Value::String(name.to_owned()))]);
+        let mut cursor = self.fetch(&stored)?;
+        if let Some(row) = cursor.next()? {
+            let id_search = integer(&row[0])?;
+            let query = Update::new()
+                .set(Searches(searches::Query), Value::String(search.to_owned()))
+                .filter(EqVal(Searches(searches::IdSearch), Value::Integer(id_search)))
+                .build();
+            self.run(&query)?;
+            return Ok(id_search);
+        }
+        let query = Insert::new()
+            .value(Searches(searches::Name), Value::String(name.to_owned()))
+            .value(Searches(searches::Query), Value::String(search.to_owned()))
+            .build();
+        info!(target: "MusicDatabase", "Saving search {:?}", name);
+        self.run(&query)?;
+        self.last_insert_id()
+    }
+
+    /// Removes the saved search with the given id from the database.
+    pub fn delete_search(&self, id_search: i64) -> Result<()> {
+        let query = Delete::from(Table::Searches)
+            .filter(EqVal(Searches(searches::IdSearch), Value::Integer(id_search)))
+            .build();
+        self.run(&query)
+    }
+
+    /// Returns the queries of the recent searches, the latest first. A query searched again is
+    /// stored again, so the rows are in the order of their ids.
+    pub fn recent_searches(&self) -> Result<Vec<String>> {
+        let query = Select::new(&[RecentSearches(recent_searches::Query)])
+            .order_by(Order::Desc(Field::Column(RecentSearches(recent_searches::IdRecent))))
+            .build();
+        let mut cursor = self.fetch(&query)?;
+        let mut recent = Vec::new();
+        while let Some(row) = cursor.next()? {
+            recent.extend(text(&row[0]));
+        }
+        Ok(recent)
+    }
+
+    /// Adds a query to the recent searches, or moves it first if it was already searched. Only
+    /// the latest `RECENT_SEARCHES` are kept.
+    pub fn add_recent_search(&self, search: &str) -> Result<()> {
+        let query = Delete::from(Table::RecentSearches)
+            .filter(EqVal(RecentSearches(recent_searches::Query), Value::String(search.to_owned())))
+            .build();
+        self.run(&query)?;
+        let query = Insert::new()
+            .value(RecentSearches(recent_searches::Query), Value::String(search.to_owned()))
+            .value(RecentSearches(recent_searches::Searched), Value::Integer(now()))
+            .build();
+        self.run(&query)?;
+        let old = Select::new(&[RecentSearches(recent_searches::IdRecent)])
+            .order_by(Order::Desc(Field::Column(RecentSearches(recent_searches::IdRecent))))
+            .offset(RECENT_SEARCHES)
+            .build();
+        let mut cursor = self.fetch(&old)?;
+        let mut ids = Vec::new();
+        while let Some(row) = cursor.next()? {
+            ids.push(Value::Integer(integer(&row[0])?));
+        }
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let query = Delete::from(Table::RecentSearches)
+            .filter(Conditional::In(RecentSearches(recent_searches::IdRecent), ids))
+            .build();
+        self.run(&query)
+    }
+
+    /// Removes the song with the given id from the database, with its playbacks. Its performer
+    /// and album remain stored.
+    pub fn delete_song(&self, id_rola: i64) -> Result<()> {
+        info!(target: "MusicDatabase", "Deleting song {}", id_rola);
+        self.transaction(|database| {
+            let query = Delete::from(Table::History)
+                .filter(EqVal(History(history::IdRola), Value::Integer(id_rola)))
+                .build();
+            database.run(&query)?;
+            let query = Delete::from(Table::Rolas)
+                .filter(EqVal(Rolas(rolas::IdRola), Value::Integer(id_rola)))
+                .build();
+            database.run(&query)
+        })
+    }
+
+    /// Returns the audio hash of the song stored with the given path, if the song was stored
+    /// from its file as it was modified at the given time, so the audio of a file that did not
+    /// change is not read again.
+    pub fn stored_audio_hash(&self, path: &str, modified: i64) -> Result<Option<String>> {
         let query = query_manager::select(
-            &[Rolas("id_rola")],
-            &[EqVal(Rolas("title"), title)]
+            &[Rolas(rolas::AudioHash)],
+            &[EqVal(Rolas(rolas::Path), Value::String(path.to_owned())),
+                EqVal(Rolas(rolas::Modified), Value::Integer(modified))]
         );
-        let mut cursor = self.query(&query).unwrap();
-        cursor.next().unwrap().is_some()
+        let mut cursor = self.fetch(&query)?;
+        match cursor.next()? {
+            Some(row) => Ok(text(&row[0])),
+            None => Ok(None),
+        }
     }
 
-    /// Checks if the given album exists in database.
-    pub fn album_in_database(&self, album: &path::PathBuf) -> bool {
-        let album_path = album.to_str().unwrap().to_string();
+    /// Returns the id of the song stored with the given path, if exists.
+    pub fn song_id(&self, path: &str) -> Result<Option<i64>> {
         let query = query_manager::select(
-            &[Albums("id_album")],
-            &[EqVal(Albums("path"), &album_path)]
+            &[Rolas(rolas::IdRola)],
+            &[EqVal(Rolas(rolas::Path), Value::String(path.to_owned()))]
         );
-        let mut cursor = self.query(&query).unwrap();
-        cursor.next().unwrap().is_some()
+        let mut cursor = self.fetch(&query)?;
+        match cursor.next()? {
+            Some(row) => Ok(Some(integer(&row[0])?)),
+            None => Ok(None),
+        }
     }
 
-    /// Given an SQL statement, returns the resulting rows of the query as a strings vector.
-    pub fn search_songs(&self, query: &str) -> Vec<String>{
-        let mut cursor = self.query(&query).unwrap();
-        let mut songs = Vec::new();
-        while let Some(row) = cursor.next().unwrap() {
-            let title = row[0].as_string().unwrap();
-            songs.push(title.to_owned());
+    /// Given a table-column and a value, returns the id of the first row of the table-column's
+    /// table with that value. If the value does not exists in database, it is inserted.
+    pub fn foreign_key(&self, column: TC, column_value: &str) -> Result<i64> {
+        let id_column = id_column(column)?;
+        let conditional = EqVal(column, Value::String(column_value.to_owned()));
+
+        let query = query_manager::select(&[id_column], &[conditional]);
+        let mut cursor = self.fetch(&query)?;
+        if let Some(row) = cursor.next()? {
+            integer(&row[0])
+        }
+        else {
+            self.insert_and_get_id(column, column_value)
+        }
+    }
+
+    /// Given a table-column and a value, creates the corresponding new entry in the table-column's
+    /// table, and returns its id.
+    pub fn insert_and_get_id(&self, column: TC, column_value: &str) -> Result<i64> {
+        id_column(column)?;
+        let query = Insert::new()
+            .value(column, Value::String(column_value.to_owned()))
+            .build();
+        self.run(&query)?;
+        self.last_insert_id()
+    }
+
+    /// Checks if the given song exists in database, by its path.
+    pub fn song_in_database(&self, song: &MusicFile) -> Result<bool> {
+        Ok(self.song_id(&song.path())?.is_some())
+    }
+
+    /// Given a statement created by the query manager that selects the ids of songs, returns
+    /// the ids.
+    pub fn search_songs(&self, query: &Query) -> Result<Vec<i64>> {
+        let mut cursor = self.fetch(query)?;
+        let mut ids = Vec::new();
+        while let Some(row) = cursor.next()? {
+            ids.push(integer(&row[0])?);
+        }
+        Ok(ids)
+    }
+
+}
+
+/// Columns read by `song_from_row`, in order.
+pub const SONG_COLUMNS: [TC; 22] = [
+    Rolas(rolas::IdRola), Rolas(rolas::Path), Rolas(rolas::Title), Rolas(rolas::Track), Rolas(rolas::Year),
+    Rolas(rolas::Genre), Rolas(rolas::Duration), Rolas(rolas::Disc), Rolas(rolas::Rating),
+    Rolas(rolas::PlayCount), Rolas(rolas::SkipCount), Rolas(rolas::LastPlayed), Rolas(rolas::DateAdded),
+    Performers(performers::IdPerformer), Performers(performers::IdType), Performers(performers::Name),
+    Albums(albums::IdAlbum), Albums(albums::Path), Albums(albums::Name), Albums(albums::Year),
+    Albums(albums::IdPerformer), Rolas(rolas::Bitrate),
+];
+
+/// Returns a SELECT statement of `SONG_COLUMNS`, joining the songs that are not hidden or
+/// missing with their performers and albums. It can be filtered and sorted before being passed to
+/// `MusicDatabase::songs_from`.
+pub fn songs_select() -> Select {
+    Select::new(&SONG_COLUMNS)
+        .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+        .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)))
+        .filter(visible())
+}
+
+/// Returns the conditional that matches the songs that are not hidden or missing.
+fn visible() -> Conditional {
+    Conditional::And(vec![
+        EqVal(Rolas(rolas::Hidden), Value::Integer(0)),
+        IsNull(Rolas(rolas::MissingSince)),
+    ])
+}
+
+/// Columns of a song's entry that are read from its music file, as set by `song_values`.
+const FILE_COLUMNS: [TC; 13] = [
+    Rolas(rolas::IdPerformer), Rolas(rolas::IdAlbum), Rolas(rolas::Path), Rolas(rolas::Title),
+    Rolas(rolas::Track), Rolas(rolas::Year), Rolas(rolas::Genre), Rolas(rolas::Duration),
+    Rolas(rolas::Disc), Rolas(rolas::AudioHash), Rolas(rolas::MissingSince),
+    Rolas(rolas::Modified), Rolas(rolas::Bitrate),
+];
+
+/// Columns read by `album_from_row`, in order.
+const ALBUM_COLUMNS: [TC; 5] = [
+    Albums(albums::IdAlbum), Albums(albums::Path), Albums(albums::Name), Albums(albums::Year),
+    Albums(albums::IdPerformer),
+];
+
+/// Columns read by `performer_from_row`, in order.
+const PERFORMER_COLUMNS: [TC; 3] = [
+    Performers(performers::IdPerformer), Performers(performers::IdType), Performers(performers::Name),
+];
+
+/// Milliseconds a statement waits for the database to stop being busy before failing.
+const BUSY_TIMEOUT: usize = 5000;
+
+/// Number of recent searches that are kept.
+pub const RECENT_SEARCHES: i64 = 20;
+
+/// Value stored for the title, performer, album artist and genre of a song without them.
+pub const UNKNOWN: &str = "Unknown";
+
+/// Largest difference, in seconds, between the durations of songs with the same performer and
+/// title that are reported as duplicates.
+pub const DUPLICATE_TOLERANCE: u64 = 2;
+
+/// Columns of the saved searches, in the order they are read.
+const SEARCH_COLUMNS: [TC; 3] = [
+    Searches(searches::IdSearch), Searches(searches::Name), Searches(searches::Query),
+];
+
+/// Columns read by `playlist_from_row`, in order.
+const PLAYLIST_COLUMNS: [TC; 5] = [
+    Playlists(playlists::IdPlaylist), Playlists(playlists::Name), Playlists(playlists::Rules),
+    Playlists(playlists::SortOrder), Playlists(playlists::MaxSongs),
+];
+
+/// Creates a song from a row with the values of `SONG_COLUMNS`.
+fn song_from_row(row: &[sqlite::Value]) -> Result<Song> {
+    Ok(Song {
+        id: integer(&row[0])?,
+        path: path::PathBuf::from(text(&row[1]).unwrap_or_default()),
+        title: text(&row[2]).unwrap_or_default(),
+        track: row[3].as_integer().map(|track| track as u32),
+        year: row[4].as_integer().map(|year| year as i32),
+        genre: text(&row[5]),
+        duration: row[6].as_integer().map(|duration| Duration::from_millis(duration as u64)),
+        disc: row[7].as_integer().map(|disc| disc as u32),
+        bitrate: row[21].as_integer().map(|bitrate| bitrate as u32),
+        rating: row[8].as_integer().unwrap_or(0) as u8,
+        play_count: row[9].as_integer().unwrap_or(0) as u32,
+        skip_count: row[10].as_integer().unwrap_or(0) as u32,
+        last_played: row[11].as_integer(),
+        date_added: row[12].as_integer(),
+        performer: performer_from_row(&row[13..16])?,
+        album: album_from_row(&row[16..21])?,
+    })
+}
+
+/// Returns the performer and title of a song, normalized, to find the songs that are the same
+/// recording. Songs without duration, performer or title have none.
+fn metadata_key(song: &Song) -> Option<(String, String)> {
+    if song.duration.is_none() || song.title == UNKNOWN || song.performer.name == UNKNOWN {
+        return None;
+    }
+    Some((normalize(&song.performer.name), normalize(&song.title)))
+}
+
+/// Normalizes a name to compare it with others: its words of letters and digits, in lowercase,
+/// separated by a space.
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Creates an album from a row with the values of `ALBUM_COLUMNS`.
+fn album_from_row(row: &[sqlite::Value]) -> Result<Album> {
+    Ok(Album {
+        id: integer(&row[0])?,
+        path: text(&row[1]).map(path::PathBuf::from),
+        name: text(&row[2]).unwrap_or_default(),
+        year: row[3].as_integer().map(|year| year as i32),
+        id_performer: row[4].as_integer(),
+    })
+}
+
+/// Creates a performer from a row with the values of `PERFORMER_COLUMNS`.
+fn performer_from_row(row: &[sqlite::Value]) -> Result<Performer> {
+    Ok(Performer {
+        id: integer(&row[0])?,
+        kind: PerformerType::from_id(row[1].as_integer().unwrap_or(2)),
+        name: text(&row[2]).unwrap_or_default(),
+    })
+}
+
+/// Creates a smart playlist from a row with the values of `PLAYLIST_COLUMNS`.
+fn playlist_from_row(row: &[sqlite::Value]) -> Result<SmartPlaylist> {
+    Ok(SmartPlaylist {
+        id: integer(&row[0])?,
+        name: text(&row[1]).unwrap_or_default(),
+        rules: text(&row[2]).unwrap_or_default(),
+        order: PlaylistOrder::from_name(&text(&row[3]).unwrap_or_default())?,
+        limit: row[4].as_integer(),
+    })
+}
+
+/// Returns the conditionals of the playbacks that started in a period.
+fn period_conditionals(period: &Period) -> Vec<Conditional> {
+    vec![
+        Ge(History(history::Started), Value::Integer(period.from)),
+        Lt(History(history::Started), Value::Integer(period.to)),
+    ]
+}
+
+/// Builds a query of the most played rows of a period, given a grouped query that counts
+/// playbacks: the most played first, at most `limit` if there is one.
+fn top_query(mut query: Select, period: &Period, limit: Option<i64>) -> Query {
+    for conditional in period_conditionals(period) {
+        query = query.filter(conditional);
+    }
+    query = query.order_by(Order::Desc(Field::Count(History(history::IdHistory))));
+    if let Some(limit) = limit {
+        query = query.limit(limit);
+    }
+    query.build()
+}
+
+/// Returns a duration in milliseconds.
+fn duration_millis(duration: Duration) -> i64 {
+    (duration.as_secs() * 1000 + u64::from(duration.subsec_millis())) as i64
+}
+
+/// Reads an integer value from a row, failing if the value has another type.
+fn integer(value: &sqlite::Value) -> Result<i64> {
+    match value.as_integer() {
+        Some(integer) => Ok(integer),
+        None => Err(Error::Parse(format!("Expected an integer, found {:?}", value))),
+    }
+}
+
+/// Returns the table-column with the row id of the table of the given table-column.
+fn id_column(column: TC) -> Result<TC> {
+    match column.table().id_column() {
+        Some(id_column) => Ok(id_column),
+        None => Err(Error::Parse(format!("Table {} has no id column", column.as_table()))),
+    }
+}
+
+/// Returns an integer value, or NULL if there is none.
+fn optional_integer(value: Option<i64>) -> Value {
+    match value {
+        Some(integer) => Value::Integer(integer),
+        None => Value::Null,
+    }
+}
+
+/// Reads an optional text value from a row.
+fn text(value: &sqlite::Value) -> Option<String> {
+    value.as_string().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::schema::{TABLE_LIST, Table, TableColumn, types, in_group};
+
+    fn database() -> MusicDatabase {
+        let mut database = MusicDatabase::new();
+        database.with_database(":memory:");
+        database.connect().unwrap();
+        database
+    }
+
+    /// Returns a text column of every table with a row id.
+    fn text_column(table: Table) -> Option<TC> {
+        match table {
+            Table::Types => Some(TableColumn::Types(types::Description)),
+            Table::Performers => Some(Performers(performers::Name)),
+            Table::Persons => Some(Persons(persons::StageName)),
+            Table::Groups => Some(Groups(groups::Name)),
+            Table::Albums => Some(Albums(albums::Name)),
+            Table::Rolas => Some(Rolas(rolas::Title)),
+            Table::Playlists => Some(Playlists(playlists::Name)),
+            Table::Searches => Some(Searches(searches::Name)),
+            Table::RecentSearches => Some(RecentSearches(recent_searches::Query)),
+            Table::InGroup | Table::History => None,
+        }
+    }
+
+    #[test]
+    fn new_database_has_latest_version() {
+        let database = database();
+        assert_eq!(database.version().unwrap(), schema::MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn foreign_key_inserts_into_every_table() {
+        let database = database();
+        for table in TABLE_LIST.iter() {
+            let column = match text_column(*table) {
+                Some(column) => column,
+                None => continue,
+            };
+            let id = database.foreign_key(column, "foreign key").unwrap();
+            assert_eq!(database.foreign_key(column, "foreign key").unwrap(), id);
+
+            let query = query_manager::select(&[table.id_column().unwrap()],
+                &[EqVal(column, Value::String(String::from("foreign key")))]);
+            let mut cursor = database.fetch(&query).unwrap();
+            assert_eq!(cursor.next().unwrap().unwrap()[0].as_integer(), Some(id));
+        }
+    }
+
+    #[test]
+    fn foreign_key_finds_existing_rows() {
+        let database = database();
+        let column = TableColumn::Types(types::Description);
+        assert_eq!(database.foreign_key(column, "Group").unwrap(), PerformerType::Group.id());
+    }
+
+    #[test]
+    fn foreign_key_needs_an_id_column() {
+        let database = database();
+        assert!(database.foreign_key(TableColumn::InGroup(in_group::IdPerson), "1").is_err());
+    }
+
+    #[test]
+    fn songs_are_identified_by_path() {
+        let database = database();
+        let mut tag = ::id3::Tag::new();
+        tag.set_title("Title");
+        let file = || MusicFile::from_tag(path::PathBuf::from("/music/song.mp3"), &tag);
+        let id = match database.save_song(file()).unwrap() {
+            SongChange::Added(id) => id,
+            change => panic!("Unexpected change {:?}", change),
+        };
+        assert_eq!(database.save_song(file()).unwrap(), SongChange::Unchanged(id));
+
+        tag.set_title("New title");
+        let file = MusicFile::from_tag(path::PathBuf::from("/music/song.mp3"), &tag);
+        assert_eq!(database.save_song(file).unwrap(), SongChange::Updated(id));
+        assert_eq!(database.song(id).unwrap().unwrap().title, "New title");
+    }
+
+    #[test]
+    fn file_databases_are_shared_through_a_log() {
+        use std::{env, fs, process};
+        let directory = env::temp_dir().join(format!("music_player_rs_db_{}", process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("music.db");
+        let mut first = MusicDatabase::new();
+        first.with_database(&path.to_string_lossy()).connect().unwrap();
+        let mut second = MusicDatabase::new();
+        second.with_database(&path.to_string_lossy()).connect().unwrap();
+        let mode = first.query("PRAGMA journal_mode;").unwrap().next().unwrap()
+            .and_then(|row| text(&row[0]));
+        let mut tag = ::id3::Tag::new();
+        tag.set_title("A");
+        first.save_song(MusicFile::from_tag(path::PathBuf::from("/music/a.mp3"), &tag)).unwrap();
+        let ids = second.song_ids(&SongFilter::default());
+        drop(first);
+        drop(second);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(mode, Some(String::from("wal")));
+        assert_eq!(ids.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn null_values_are_read_in_every_row() {
+        let database = database();
+        let mut ids = Vec::new();
+        for &(file, year) in [("a.mp3", None), ("b.mp3", Some(1999)), ("c.mp3", None)].iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            if let Some(year) = year {
+                tag.set_year(year);
+            }
+            let path = path::PathBuf::from("/music").join(file);
+            match database.save_song(MusicFile::from_tag(path, &tag)).unwrap() {
+                SongChange::Added(id) => ids.push(id),
+                change => panic!("Unexpected change {:?}", change),
+            }
+        }
+        database.record_play(ids[1]).unwrap();
+        let songs = database.songs().unwrap();
+        let years: Vec<Option<i32>> = songs.iter().map(|song| song.year).collect();
+        assert_eq!(years, vec![None, Some(1999), None]);
+        let played: Vec<bool> = songs.iter().map(|song| song.last_played.is_some()).collect();
+        assert_eq!(played, vec![false, true, false]);
+    }
+
+    #[test]
+    fn albums_are_identified_by_artist_name_and_directory() {
+        let database = database();
+        let songs = [("Split/a.mp3", "One"), ("Split/b.mp3", "Two"), ("X/c.mp3", "Same"),
+            ("Y/d.mp3", "Same"), ("Z/CD1/e.mp3", "Double"), ("Z/CD2/f.mp3", "Double")];
+        let save = |file: &str, album: &str| {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            tag.set_artist("Artist");
+            tag.set_album(album);
+            database.save_song(MusicFile::from_tag(path::PathBuf::from("/music").join(file), &tag))
+                .unwrap();
+        };
+        for &(file, album) in songs.iter() {
+            save(file, album);
+        }
+        let albums = |database: &MusicDatabase| {
+            let mut albums: Vec<(String, Option<path::PathBuf>)> = database.albums().unwrap()
+                .into_iter()
+                .map(|album| (album.name, album.path))
+                .collect();
+            albums.sort();
+            albums
+        };
+        let album = |name: &str, directory: &str| {
+            (String::from(name), Some(path::PathBuf::from("/music").join(directory)))
+        };
+        assert_eq!(albums(&database), vec![album("Double", "Z"), album("One", "Split"),
+            album("Same", "X"), album("Same", "Y"), album("Two", "Split")]);
+
+        save("Split/a.mp3", "Renamed");
+        assert_eq!(albums(&database).len(), 6);
+        database.delete_empty_albums().unwrap();
+        assert_eq!(albums(&database), vec![album("Double", "Z"), album("Renamed", "Split"),
+            album("Same", "X"), album("Same", "Y"), album("Two", "Split")]);
+    }
+
+    #[test]
+    fn statistics_are_kept_when_the_tag_changes() {
+        let database = database();
+        let mut tag = ::id3::Tag::new();
+        tag.set_title("Title");
+        let file = MusicFile::from_tag(path::PathBuf::from("/music/song.mp3"), &tag);
+        let id = match database.save_song(file).unwrap() {
+            SongChange::Added(id) => id,
+            change => panic!("Unexpected change {:?}", change),
+        };
+        database.set_rating(id, 4).unwrap();
+        match database.set_rating(id, 6) {
+            Err(Error::InvalidRating(6)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match database.set_rating(id + 1, 3) {
+            Err(Error::NotFound(_)) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(database.record_play(id + 1).is_err());
+        database.record_play(id).unwrap();
+        database.record_play(id).unwrap();
+        database.record_skip(id).unwrap();
+
+        tag.set_title("New title");
+        let file = MusicFile::from_tag(path::PathBuf::from("/music/song.mp3"), &tag);
+        assert_eq!(database.save_song(file).unwrap(), SongChange::Updated(id));
+        let song = database.song(id).unwrap().unwrap();
+        assert_eq!((song.rating, song.play_count, song.skip_count), (4, 2, 1));
+        assert!(song.last_played.is_some());
+        assert!(song.date_added.is_some());
+    }
+
+    #[test]
+    fn history_queries() {
+        let database = database();
+        let mut ids = Vec::new();
+        for &(file, artist, album) in [("a.mp3", "Artist", "First"), ("b.mp3", "Artist", "First"),
+            ("c.mp3", "Other", "Second")].iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            tag.set_artist(artist);
+            tag.set_album(album);
+            let path = path::PathBuf::from("/music").join(album).join(file);
+            match database.save_song(MusicFile::from_tag(path, &tag)).unwrap() {
+                SongChange::Added(id) => ids.push(id),
+                change => panic!("Unexpected change {:?}", change),
+            }
+        }
+        let october = Period::month(2026, 10);
+        let day = 86_400;
+        let minute = Duration::from_secs(60);
+        database.record_playback(ids[0], october.from, minute, true).unwrap();
+        database.record_playback(ids[1], october.from + 60, minute, true).unwrap();
+        database.record_playback(ids[2], october.from + day, minute / 2, false).unwrap();
+        database.record_playback(ids[2], october.to, minute, true).unwrap();
+
+        let history = database.history(&october, None).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].song.id, ids[2]);
+        assert!(!history[0].completed);
+        assert_eq!(database.history(&Period::all(), Some(1)).unwrap()[0].started, october.to);
+
+        let performers: Vec<(String, i64)> = database.top_performers(&october, None).unwrap()
+            .into_iter()
+            .map(|(performer, count)| (performer.name, count))
+            .collect();
+        assert_eq!(performers, vec![(String::from("Artist"), 2), (String::from("Other"), 1)]);
+        let albums = database.top_albums(&Period::year(2026), Some(1)).unwrap();
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].1, 2);
+
+        let timeline = database.listening_timeline(&october).unwrap();
+        assert_eq!(timeline, vec![
+            DayListening { date: String::from("2026-10-01"), playbacks: 2, listened: minute * 2 },
+            DayListening { date: String::from("2026-10-02"), playbacks: 1, listened: minute / 2 },
+        ]);
+        let song = database.song(ids[2]).unwrap().unwrap();
+        assert_eq!((song.play_count, song.skip_count), (1, 1));
+    }
+
+    #[test]
+    fn deleted_songs_take_their_playbacks() {
+        let database = database();
+        let mut ids = Vec::new();
+        for file in ["a.mp3", "b.mp3"].iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(*file);
+            let path = path::PathBuf::from("/music").join(file);
+            match database.save_song(MusicFile::from_tag(path, &tag)).unwrap() {
+                SongChange::Added(id) => ids.push(id),
+                change => panic!("Unexpected change {:?}", change),
+            }
+            database.record_playback(ids[ids.len() - 1], 1_000, Duration::from_secs(60), true)
+                .unwrap();
+        }
+        database.delete_song(ids[0]).unwrap();
+        let query = Select::fields(vec![Field::Count(History(history::IdHistory))]).build();
+        let mut cursor = database.fetch(&query).unwrap();
+        assert_eq!(cursor.next().unwrap().unwrap()[0].as_integer(), Some(1));
+        assert_eq!(database.listening_timeline(&Period::all()).unwrap()[0].playbacks, 1);
+
+        let failed: Result<()> = database.transaction(|database| {
+            database.delete_song(ids[1])?;
+            Err(Error::Cancelled)
+        });
+        assert!(failed.is_err());
+        assert!(database.song(ids[1]).unwrap().is_some());
+        assert_eq!(database.history(&Period::all(), None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn playlists_are_identified_by_name() {
+        let database = database();
+        let mut playlist = SmartPlaylist {
+            id: 0,
+            name: String::from("Classics"),
+            rules: String::from("G: rock Y:<1980"),
+            order: PlaylistOrder::MostPlayed,
+            limit: Some(50),
+        };
+        let id = database.save_playlist(&playlist).unwrap();
+        playlist.id = id;
+        assert_eq!(database.playlist("Classics").unwrap(), Some(playlist.clone()));
+
+        playlist.order = PlaylistOrder::Random;
+        playlist.limit = None;
+        assert_eq!(database.save_playlist(&playlist).unwrap(), id);
+        assert_eq!(database.playlists().unwrap(), vec![playlist]);
+
+        database.delete_playlist(id).unwrap();
+        assert_eq!(database.playlist("Classics").unwrap(), None);
+    }
+
+    #[test]
+    fn searches_are_saved_and_remembered() {
+        let database = database();
+        let id = database.save_search("Rock", "G: rock").unwrap();
+        assert_eq!(database.save_search("Rock", "G: rock R:>=4").unwrap(), id);
+        database.save_search("Jazz", "G: jazz").unwrap();
+        let names: Vec<(String, String)> = database.saved_searches().unwrap().into_iter()
+            .map(|search| (search.name, search.query))
+            .collect();
+        assert_eq!(names, vec![(String::from("Jazz"), String::from("G: jazz")),
+            (String::from("Rock"), String::from("G: rock R:>=4"))]);
+        database.delete_search(id).unwrap();
+        assert_eq!(database.saved_searches().unwrap().len(), 1);
+
+        for number in 0..RECENT_SEARCHES + 2 {
+            database.add_recent_search(&format!("T: {}", number)).unwrap();
+        }
+        database.add_recent_search("T: 5").unwrap();
+        let recent = database.recent_searches().unwrap();
+        assert_eq!(recent.len(), RECENT_SEARCHES as usize);
+        assert_eq!(recent[0], "T: 5");
+        assert_eq!(recent[1], format!("T: {}", RECENT_SEARCHES + 1));
+        assert!(!recent.contains(&String::from("T: 1")));
+    }
+
+    #[test]
+    fn library_statistics() {
+        let database = database();
+        let songs = [("a.mp3", Some("Rock"), Some(1975)), ("b.mp3", Some("Rock"), Some(1991)),
+            ("c.flac", Some("Jazz"), Some(1975)), ("d.MP3", None, None)];
+        for &(file, genre, year) in songs.iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            tag.set_artist("Artist");
+            tag.set_duration(60_000);
+            if let Some(genre) = genre {
+                tag.set_genre(genre);
+            }
+            if let Some(year) = year {
+                tag.set_year(year);
+            }
+            let path = path::PathBuf::from("/music/Album").join(file);
+            database.save_song(MusicFile::from_tag(path, &tag)).unwrap();
+        }
+        let mut tag = ::id3::Tag::new();
+        tag.set_title("Other");
+        let hidden = MusicFile::from_tag(path::PathBuf::from("/music/Other/e.ogg"), &tag);
+        let hidden = database.save_song(hidden).unwrap();
+        let missing = MusicFile::from_tag(path::PathBuf::from("/music/Other/f.ogg"), &tag);
+        let missing = database.save_song(missing).unwrap();
+        match (hidden, missing) {
+            (SongChange::Added(hidden), SongChange::Added(missing)) => {
+                database.set_hidden(hidden, true).unwrap();
+                database.set_missing(missing).unwrap();
+            },
+            changes => panic!("Unexpected changes {:?}", changes),
         }
-        songs
+        let statistics = database.statistics().unwrap();
+        assert_eq!((statistics.songs, statistics.albums, statistics.performers, statistics.genres),
+            (4, 1, 1, 3));
+        assert_eq!((statistics.hidden, statistics.missing), (1, 1));
+        assert_eq!(statistics.duration, Duration::from_secs(240));
+        assert_eq!(statistics.size, 0);
+        assert_eq!(statistics.formats, vec![(String::from("mp3"), 3), (String::from("flac"), 1)]);
+        assert_eq!((statistics.missing_tags, statistics.missing_art), (1, 4));
+        assert_eq!(statistics.years, vec![(1975, 2), (1991, 1)]);
     }
 
+    #[test]
+    fn duplicates_are_merged_or_hidden() {
+        let database = database();
+        let songs = [("a.mp3", "The Beatles", "Help!", 138_000, Some("1")),
+            ("b.mp3", "the beatles", "Help", 139_500, None),
+            ("c.mp3", "The Beatles", "Help!", 200_000, Some("2")),
+            ("d.mp3", "Queen", "Other title", 180_000, Some("2"))];
+        let mut ids = Vec::new();
+        for &(file, artist, title, duration, hash) in songs.iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(title);
+            tag.set_artist(artist);
+            tag.set_duration(duration);
+            let path = path::PathBuf::from("/music").join(file);
+            let mut music_file = MusicFile::from_tag(path, &tag);
+            music_file.set_audio_hash(hash.map(String::from));
+            ids.push(match database.save_song(music_file).unwrap() {
+                SongChange::Added(id) => id,
+                change => panic!("Unexpected change {:?}", change),
+            });
+        }
+        let tolerance = Duration::from_secs(DUPLICATE_TOLERANCE);
+        let groups: Vec<(DuplicateReason, Vec<i64>)> = database.duplicates(tolerance).unwrap()
+            .into_iter()
+            .map(|group| (group.reason, group.songs.iter().map(|song| song.id).collect()))
+            .collect();
+        assert_eq!(groups, vec![(DuplicateReason::SameAudio, vec![ids[2], ids[3]]),
+            (DuplicateReason::SameMetadata, vec![ids[0], ids[1]])]);
+
+        database.set_hidden(ids[3], true).unwrap();
+        assert_eq!(database.duplicates(tolerance).unwrap().len(), 1);
+        assert_eq!(database.songs().unwrap().len(), 3);
+
+        database.set_rating(ids[1], 4).unwrap();
+        database.record_playback(ids[0], 1_000, Duration::from_secs(60), true).unwrap();
+        database.record_playback(ids[1], 2_000, Duration::from_secs(60), true).unwrap();
+        database.merge_songs(ids[0], &[ids[1]]).unwrap();
+        let song = database.song(ids[0]).unwrap().unwrap();
+        assert_eq!((song.rating, song.play_count), (4, 2));
+        assert_eq!(database.song(ids[1]).unwrap(), None);
+        assert_eq!(database.history(&Period::all(), None).unwrap().len(), 2);
+        assert!(database.duplicates(tolerance).unwrap().is_empty());
+        assert!(database.merge_songs(ids[0], &[ids[1]]).is_err());
+    }
+
+    #[test]
+    fn moved_songs_are_found_and_missing_ones_are_listed() {
+        let database = database();
+        let file = |path: &str, title: &str, hash: Option<&str>| {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(title);
+            tag.set_artist("Artist");
+            tag.set_album("Album");
+            tag.set_duration(200_000);
+            let mut music_file = MusicFile::from_tag(path::PathBuf::from(path), &tag);
+            music_file.set_audio_hash(hash.map(String::from));
+            music_file
+        };
+        let id = |change: SongChange| match change {
+            SongChange::Added(id) => id,
+            change => panic!("Unexpected change {:?}", change),
+        };
+        let by_hash = id(database.save_song(file("/old/a.mp3", "A", Some("1"))).unwrap());
+        let by_tags = id(database.save_song(file("/old/b.mp3", "B", None)).unwrap());
+        let missing = id(database.save_song(file("/old/c.mp3", "C", None)).unwrap());
+        let moved = [
+            id(database.save_song(file("/new/renamed.mp3", "Other", Some("1"))).unwrap()),
+            id(database.save_song(file("/new/b.mp3", "b", None)).unwrap()),
+        ];
+        assert_eq!(database.find_moved(by_hash, &moved).unwrap(), Some(moved[0]));
+        assert_eq!(database.find_moved(by_tags, &moved).unwrap(), Some(moved[1]));
+        assert_eq!(database.find_moved(missing, &moved).unwrap(), None);
+        assert_eq!(database.find_moved(missing, &[]).unwrap(), None);
+
+        database.record_play(by_tags).unwrap();
+        database.set_missing(by_tags).unwrap();
+        database.move_song(by_tags, moved[1]).unwrap();
+        let song = database.song(by_tags).unwrap().unwrap();
+        assert_eq!((song.path.to_string_lossy().as_ref(), song.title.as_str(), song.play_count),
+            ("/new/b.mp3", "b", 1));
+        assert_eq!(database.song(moved[1]).unwrap(), None);
+
+        database.set_missing(missing).unwrap();
+        database.set_missing(by_hash).unwrap();
+        database.set_hidden(by_hash, true).unwrap();
+        assert_eq!(database.song(missing).unwrap(), None);
+        let missing_songs = database.missing_songs().unwrap();
+        assert_eq!(missing_songs.len(), 1);
+        assert_eq!(missing_songs[0].0.id, missing);
+        assert_eq!(database.stored_songs().unwrap().len(), 4);
+
+        database.save_song(file("/old/c.mp3", "C", None)).unwrap();
+        assert!(database.missing_songs().unwrap().is_empty());
+        assert!(database.song(missing).unwrap().is_some());
+    }
+
+    #[test]
+    fn browser_counts() {
+        let database = database();
+        let songs = [("a.mp3", "Rock", "Artist", "First"), ("b.mp3", "Rock", "Artist", "Second"),
+            ("c.mp3", "Jazz", "Artist", "First"), ("d.mp3", "Rock", "Other", "Third")];
+        for &(file, genre, artist, album) in songs.iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            tag.set_genre(genre);
+            tag.set_artist(artist);
+            tag.set_album(album);
+            let path = path::PathBuf::from("/music").join(album).join(file);
+            database.save_song(MusicFile::from_tag(path, &tag)).unwrap();
+        }
+        assert_eq!(database.genre_counts().unwrap(),
+            vec![(String::from("Jazz"), 1), (String::from("Rock"), 3)]);
+
+        let mut filter = SongFilter { genre: Some(String::from("Rock")), ..SongFilter::default() };
+        let performers: Vec<(String, i64)> = database.performer_counts(&filter).unwrap().into_iter()
+            .map(|(performer, count)| (performer.name, count))
+            .collect();
+        assert_eq!(performers, vec![(String::from("Artist"), 2), (String::from("Other"), 1)]);
+
+        filter.id_performer = Some(database.save_performer("Artist").unwrap());
+        let albums: Vec<(String, i64)> = database.album_counts(&filter).unwrap().into_iter()
+            .map(|(album, count)| (album.name, count))
+            .collect();
+        assert_eq!(albums, vec![(String::from("First"), 1), (String::from("Second"), 1)]);
+        assert_eq!(database.song_ids(&filter).unwrap().len(), 2);
+
+        let entries = database.album_entries(&SongFilter::default()).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].album.name, "First");
+        assert_eq!(entries[0].performer, Some(String::from("Artist")));
+        assert_eq!(entries[0].songs, 2);
+        assert_eq!(entries[0].song_path, Some(path::PathBuf::from("/music/First/a.mp3")));
+
+        let jazz = SongFilter { genre: Some(String::from("Jazz")), ..SongFilter::default() };
+        database.set_hidden(database.song_ids(&jazz).unwrap()[0], true).unwrap();
+        assert_eq!(database.genre_counts().unwrap(), vec![(String::from("Rock"), 3)]);
+    }
 }
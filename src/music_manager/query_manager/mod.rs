@@ -1,136 +1,139 @@
-use std::{fs::File, io::{Read, Error, ErrorKind}, collections::HashSet};
+/// Statement builders module.
+pub mod statement;
 
-/// Creates a SQL SELECT statement, given an array of tables-columns and
-/// the conditionals that should be satisfied
-pub fn select(columns: &[TableColumn], conditionals: &[Conditional]) -> String {
-    let mut query = String::from("SELECT ");
-
-    let mut columns_iter = columns.iter().peekable();
-    while let Some(column) = columns_iter.next() {
-        query += &column.to_string();
-        if columns_iter.peek().is_some() {
-            query += ", ";
-        }
-    }
-    query += " FROM ";
-    query += &get_tables_from_columns(columns);
+pub use self::statement::{Query, Select, Insert, Update, Delete, Field, Order, Join};
+pub use sqlite::Value;
 
-    if conditionals.len() > 0 {
-        query += " WHERE ";
+pub use super::schema::{Table, TableColumn};
 
-        let mut conditionals_iter = conditionals.iter().peekable();
-        while let Some(conditional) = conditionals_iter.next() {
-            query += &conditional.to_string();
-            if conditionals_iter.peek().is_some() {
-                query += " AND ";
-            }
-        }
+/// Creates a SQL SELECT statement, given an array of tables-columns and
+/// the conditionals that should be satisfied
+pub fn select(columns: &[TableColumn], conditionals: &[Conditional]) -> Query {
+    let mut select = Select::new(columns);
+    for conditional in conditionals {
+        select = select.filter(conditional.clone());
     }
-    query
+    select.build()
 }
 
 /// Returns a string which contains the tables where the information will be taken, separated by
 /// commas.
 pub fn get_tables_from_columns(columns: &[TableColumn]) -> String {
-    let mut tables = String::new();
-    let mut used_tables = HashSet::new();
-    let mut columns_iter = columns.iter().peekable();
-    while let Some(column) = columns_iter.next() {
-        if used_tables.contains(&column.as_table()) {
-            continue;
+    let mut tables: Vec<&str> = Vec::new();
+    for column in columns {
+        if !tables.contains(&column.as_table()) {
+            tables.push(column.as_table());
         }
-        tables += &column.as_table();
-        if columns_iter.peek().is_some() {
-            tables += ", ";
-        }
-        used_tables.insert(column.as_table());
     }
-    tables
-}
-
-/// Returns a string which contains neccessary statements from the tables.sql file to create
-/// the database.
-pub fn create_database() -> Result<String, Error> {
-    let mut tables_file = File::open("./tables.sql")?;
-    let mut buffer = String::new();
-    tables_file.read_to_string(&mut buffer)?;
-    Ok(buffer)
+    tables.join(", ")
 }
 
 /// Types of conditionals for a SQL statement, such as equivalence between two table-columns,
-/// checking if a column has an specific value and checking if a column has an approximate value
-pub enum Conditional<'a> {
-    Eq(TableColumn<'a>, TableColumn<'a>),
-    EqVal(TableColumn<'a>, &'a str),
-    Like(TableColumn<'a>, &'a str),
+/// comparing a column with a value, checking if a column has an approximate value, starts with a
+/// value or is one of several values, and grouping conditionals with AND, OR and NOT.
+/// Values are never written in the statement, they are bound as parameters.
+#[derive(Clone, Debug)]
+pub enum Conditional {
+    Eq(TableColumn, TableColumn),
+    EqVal(TableColumn, Value),
+    NotEqVal(TableColumn, Value),
+    Lt(TableColumn, Value),
+    Le(TableColumn, Value),
+    Gt(TableColumn, Value),
+    Ge(TableColumn, Value),
+    Like(TableColumn, String),
+    StartsWith(TableColumn, String),
+    In(TableColumn, Vec<Value>),
+    IsNull(TableColumn),
+    And(Vec<Conditional>),
+    Or(Vec<Conditional>),
+    Not(Box<Conditional>),
 }
 
-impl <'a> ToString for Conditional<'a> {
+impl Conditional {
 
-    fn to_string(&self) -> String {
+    /// Appends the conditional to a SQL statement, and its values to the statement parameters.
+    pub fn write(&self, sql: &mut String, parameters: &mut Vec<Value>) {
         match self {
-            Conditional::Eq(table1, table2) => format!("{} = {}", table1.to_string(), table2.to_string()),
-            Conditional::EqVal(table, value) => format!("{} = '{}'", table.to_string(), value),
-            Conditional::Like(table, value) => format!("{} LIKE '%{}%'", table.to_string(), value),
+            Conditional::Eq(table1, table2) => {
+                *sql += &format!("{} = {}", table1.to_string(), table2.to_string());
+            },
+            Conditional::EqVal(table, value) => write_comparison(sql, parameters, table, "=", value),
+            Conditional::NotEqVal(table, value) => write_comparison(sql, parameters, table, "<>", value),
+            Conditional::Lt(table, value) => write_comparison(sql, parameters, table, "<", value),
+            Conditional::Le(table, value) => write_comparison(sql, parameters, table, "<=", value),
+            Conditional::Gt(table, value) => write_comparison(sql, parameters, table, ">", value),
+            Conditional::Ge(table, value) => write_comparison(sql, parameters, table, ">=", value),
+            Conditional::Like(table, value) => {
+                let pattern = Value::String(format!("%{}%", escape_like(value)));
+                write_like(sql, parameters, table, &pattern);
+            },
+            Conditional::StartsWith(table, value) => {
+                let pattern = Value::String(format!("{}%", escape_like(value)));
+                write_like(sql, parameters, table, &pattern);
+            },
+            Conditional::In(table, values) => {
+                if values.is_empty() {
+                    *sql += "0";
+                    return;
+                }
+                let placeholders: Vec<&str> = values.iter().map(|_| "?").collect();
+                *sql += &format!("{} IN ({})", table.to_string(), placeholders.join(", "));
+                parameters.extend(values.iter().cloned());
+            },
+            Conditional::IsNull(table) => *sql += &format!("{} IS NULL", table.to_string()),
+            Conditional::And(conditionals) => write_group(sql, parameters, conditionals, " AND ", "1"),
+            Conditional::Or(conditionals) => write_group(sql, parameters, conditionals, " OR ", "0"),
+            Conditional::Not(conditional) => {
+                *sql += "NOT (";
+                conditional.write(sql, parameters);
+                *sql += ")";
+            },
         }
     }
 }
 
-/// Table-columns in the music player database, that can be used in the SQL statements generated by
-/// the query manager.
-pub enum TableColumn<'a> {
-    Types(&'a str),
-    Performers(&'a str),
-    Persons(&'a str),
-    Groups(&'a str),
-    Albums(&'a str),
-    Rolas(&'a str),
-    InGroup(&'a str),
+/// Appends the comparison between a column and a value to a SQL statement.
+fn write_comparison(sql: &mut String, parameters: &mut Vec<Value>, table: &TableColumn,
+    operator: &str, value: &Value) {
+    *sql += &format!("{} {} ?", table.to_string(), operator);
+    parameters.push(value.clone());
 }
 
-impl <'a> TableColumn<'a> {
-
-    /// Returns the table from the enum as string.
-    pub fn as_table(&self) -> &str {
-        match self {
-            TableColumn::Types(_) => "types",
-            TableColumn::Performers(_) => "performers",
-            TableColumn::Persons(_) => "persons",
-            TableColumn::Groups(_) => "groups",
-            TableColumn::Albums(_) => "albums",
-            TableColumn::Rolas(_) => "rolas",
-            TableColumn::InGroup(_) => "in_group",
-        }
-    }
+/// Appends the LIKE comparison between a column and a pattern to a SQL statement, with the
+/// backslash as escape character of the pattern.
+fn write_like(sql: &mut String, parameters: &mut Vec<Value>, table: &TableColumn, pattern: &Value) {
+    *sql += &format!("{} LIKE ? ESCAPE '\\'", table.to_string());
+    parameters.push(pattern.clone());
+}
 
-    /// Given a string, creates the corresponding table-column enum.
-    pub fn from_str(table: &'a str, column: &'a str) -> Result<TableColumn<'a>, Error> {
-        match table {
-            "types" | "type" => Ok(TableColumn::Types(column)),
-            "performers" | "performer" => Ok(TableColumn::Performers(column)),
-            "persons" | "person" => Ok(TableColumn::Persons(column)),
-            "groups" | "group" => Ok(TableColumn::Groups(column)),
-            "albums" | "album" => Ok(TableColumn::Albums(column)),
-            "rolas" | "rola" => Ok(TableColumn::Rolas(column)),
-            "in_group" => Ok(TableColumn::InGroup(column)),
-            _ => Err(Error::new(ErrorKind::Other, "Error parsing table"))
+/// Escapes the wildcards of LIKE patterns ("%" and "_") and the escape character in a text, so
+/// it only matches itself.
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '%' || c == '_' || c == '\\' {
+            escaped.push('\\');
         }
+        escaped.push(c);
     }
+    escaped
 }
 
-
-impl <'a> ToString for TableColumn<'a> {
-
-    /// Returns a string representation of the table-column to be used by the query manager.
-    fn to_string(&self) -> String {
-        match self {
-            TableColumn::Types(column) => format!("types.{}", column),
-            TableColumn::Performers(column) => format!("performers.{}", column),
-            TableColumn::Persons(column) => format!("persons.{}", column),
-            TableColumn::Groups(column) => format!("groups.{}", column),
-            TableColumn::Albums(column) => format!("albums.{}", column),
-            TableColumn::Rolas(column) => format!("rolas.{}", column),
-            TableColumn::InGroup(column) => format!("in_group.{}", column),
+/// Appends a parenthesized group of conditionals to a SQL statement. An empty group is written as
+/// the given default, so `And` of nothing is true and `Or` of nothing is false.
+fn write_group(sql: &mut String, parameters: &mut Vec<Value>, conditionals: &[Conditional],
+    separator: &str, default: &str) {
+    if conditionals.is_empty() {
+        *sql += default;
+        return;
+    }
+    *sql += "(";
+    for (i, conditional) in conditionals.iter().enumerate() {
+        if i > 0 {
+            *sql += separator;
         }
+        conditional.write(sql, parameters);
     }
+    *sql += ")";
 }
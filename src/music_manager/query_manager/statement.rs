@@ -0,0 +1,471 @@
+use super::{Table, TableColumn, Conditional, Value, get_tables_from_columns};
+
+/// A SQL statement and the values bound to its parameters, ready to be executed by the music
+/// database.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    sql: String,
+    parameters: Vec<Value>,
+}
+
+impl Query {
+
+    /// Creates a new query from a SQL statement and the values of its parameters.
+    pub fn new(sql: String, parameters: Vec<Value>) -> Query {
+        Query {
+            sql: sql,
+            parameters: parameters,
+        }
+    }
+
+    /// Returns the SQL statement, with a `?` for every parameter.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the values bound to the statement parameters, in order.
+    pub fn parameters(&self) -> &[Value] {
+        &self.parameters
+    }
+}
+
+/// Fields that can be selected or used to sort the results of a SELECT statement: plain
+/// table-columns or aggregates over them.
+#[derive(Clone, Debug)]
+pub enum Field {
+    Column(TableColumn),
+    Count(TableColumn),
+    CountDistinct(TableColumn),
+    Sum(TableColumn),
+    Min(TableColumn),
+    Max(TableColumn),
+}
+
+impl Field {
+
+    /// Returns the table-column the field is computed from.
+    pub fn column(&self) -> &TableColumn {
+        match self {
+            Field::Column(column) => column,
+            Field::Count(column) => column,
+            Field::CountDistinct(column) => column,
+            Field::Sum(column) => column,
+            Field::Min(column) => column,
+            Field::Max(column) => column,
+        }
+    }
+}
+
+impl ToString for Field {
+
+    fn to_string(&self) -> String {
+        match self {
+            Field::Column(column) => column.to_string(),
+            Field::Count(column) => format!("COUNT({})", column.to_string()),
+            Field::CountDistinct(column) => format!("COUNT(DISTINCT {})", column.to_string()),
+            Field::Sum(column) => format!("SUM({})", column.to_string()),
+            Field::Min(column) => format!("MIN({})", column.to_string()),
+            Field::Max(column) => format!("MAX({})", column.to_string()),
+        }
+    }
+}
+
+/// Sorting orders for the results of a SELECT statement.
+#[derive(Clone, Debug)]
+pub enum Order {
+    Asc(Field),
+    Desc(Field),
+    Random,
+}
+
+impl ToString for Order {
+
+    fn to_string(&self) -> String {
+        match self {
+            Order::Asc(field) => format!("{} ASC", field.to_string()),
+            Order::Desc(field) => format!("{} DESC", field.to_string()),
+            Order::Random => String::from("RANDOM()"),
+        }
+    }
+}
+
+/// Explicit joins of a SELECT statement. The table of the second table-column is joined,
+/// matching rows where both table-columns are equal.
+#[derive(Clone, Debug)]
+pub enum Join {
+    Inner(TableColumn, TableColumn),
+    Left(TableColumn, TableColumn),
+}
+
+impl Join {
+
+    /// Returns the joined table.
+    pub fn as_table(&self) -> &'static str {
+        match self {
+            Join::Inner(_, column) => column.as_table(),
+            Join::Left(_, column) => column.as_table(),
+        }
+    }
+}
+
+impl ToString for Join {
+
+    fn to_string(&self) -> String {
+        match self {
+            Join::Inner(column1, column2) => format!("JOIN {} ON {} = {}", column2.as_table(),
+                column1.to_string(), column2.to_string()),
+            Join::Left(column1, column2) => format!("LEFT JOIN {} ON {} = {}",
+                column2.as_table(), column1.to_string(), column2.to_string()),
+        }
+    }
+}
+
+/// Builder of SQL SELECT statements. The tables are taken from the selected fields, except the
+/// ones that are explicitly joined.
+#[derive(Clone, Debug)]
+pub struct Select {
+    fields: Vec<Field>,
+    distinct: bool,
+    joins: Vec<Join>,
+    conditionals: Vec<Conditional>,
+    group_by: Vec<TableColumn>,
+    order_by: Vec<Order>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl Select {
+
+    /// Creates a new SELECT statement of the given table-columns.
+    pub fn new(columns: &[TableColumn]) -> Select {
+        Select::fields(columns.iter().cloned().map(Field::Column).collect())
+    }
+
+    /// Creates a new SELECT statement of the given fields, which may include aggregates.
+    pub fn fields(fields: Vec<Field>) -> Select {
+        Select {
+            fields: fields,
+            distinct: false,
+            joins: Vec::new(),
+            conditionals: Vec::new(),
+            group_by: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        }
+    }
+
+    /// Removes duplicated rows from the results.
+    pub fn distinct(mut self) -> Select {
+        self.distinct = true;
+        self
+    }
+
+    /// Adds an explicit join.
+    pub fn join(mut self, join: Join) -> Select {
+        self.joins.push(join);
+        self
+    }
+
+    /// Adds a conditional the results should satisfy. Conditionals are joined with AND.
+    pub fn filter(mut self, conditional: Conditional) -> Select {
+        self.conditionals.push(conditional);
+        self
+    }
+
+    /// Groups the results by a table-column, to be used with aggregate fields.
+    pub fn group_by(mut self, column: TableColumn) -> Select {
+        self.group_by.push(column);
+        self
+    }
+
+    /// Sorts the results. Orders are applied in the order they are added.
+    pub fn order_by(mut self, order: Order) -> Select {
+        self.order_by.push(order);
+        self
+    }
+
+    /// Returns at most the given number of rows.
+    pub fn limit(mut self, limit: i64) -> Select {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skips the given number of rows.
+    pub fn offset(mut self, offset: i64) -> Select {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Returns the tables of the FROM clause, separated by commas.
+    fn tables(&self) -> String {
+        let columns: Vec<TableColumn> = self.fields.iter()
+            .map(|field| *field.column())
+            .filter(|column| !self.joins.iter().any(|join| join.as_table() == column.as_table()))
+            .collect();
+        get_tables_from_columns(&columns)
+    }
+
+    /// Creates the SQL statement with its parameters.
+    pub fn build(&self) -> Query {
+        let mut sql = String::from("SELECT ");
+        let mut parameters = Vec::new();
+        if self.distinct {
+            sql += "DISTINCT ";
+        }
+        let fields: Vec<String> = self.fields.iter().map(Field::to_string).collect();
+        sql += &fields.join(", ");
+        sql += " FROM ";
+        sql += &self.tables();
+        for join in &self.joins {
+            sql += " ";
+            sql += &join.to_string();
+        }
+        write_where(&mut sql, &mut parameters, &self.conditionals);
+        if !self.group_by.is_empty() {
+            let columns: Vec<String> = self.group_by.iter().map(TableColumn::to_string).collect();
+            sql += " GROUP BY ";
+            sql += &columns.join(", ");
+        }
+        if !self.order_by.is_empty() {
+            let orders: Vec<String> = self.order_by.iter().map(Order::to_string).collect();
+            sql += " ORDER BY ";
+            sql += &orders.join(", ");
+        }
+        if let Some(limit) = self.limit {
+            sql += " LIMIT ?";
+            parameters.push(Value::Integer(limit));
+        }
+        if let Some(offset) = self.offset {
+            if self.limit.is_none() {
+                sql += " LIMIT -1";
+            }
+            sql += " OFFSET ?";
+            parameters.push(Value::Integer(offset));
+        }
+        Query::new(sql, parameters)
+    }
+}
+
+/// Builder of SQL INSERT statements. The table is taken from the table-columns.
+#[derive(Clone, Debug)]
+pub struct Insert {
+    values: Vec<(TableColumn, Value)>,
+}
+
+impl Insert {
+
+    /// Creates a new INSERT statement without values.
+    pub fn new() -> Insert {
+        Insert {
+            values: Vec::new(),
+        }
+    }
+
+    /// Sets the value of a table-column in the new row.
+    pub fn value(mut self, column: TableColumn, value: Value) -> Insert {
+        self.values.push((column, value));
+        self
+    }
+
+    /// Creates the SQL statement with its parameters.
+    pub fn build(&self) -> Query {
+        let table = self.values.first().map(|(column, _)| column.as_table()).unwrap_or("");
+        let columns: Vec<&str> = self.values.iter().map(|(column, _)| column.as_column()).collect();
+        let placeholders: Vec<&str> = self.values.iter().map(|_| "?").collect();
+        let sql = format!("INSERT INTO {} ({}) VALUES ({})", table, columns.join(", "),
+            placeholders.join(", "));
+        Query::new(sql, self.values.iter().map(|(_, value)| value.clone()).collect())
+    }
+}
+
+/// Builder of SQL UPDATE statements. The table is taken from the table-columns.
+#[derive(Clone, Debug)]
+pub struct Update {
+    values: Vec<(TableColumn, Value)>,
+    increments: Vec<TableColumn>,
+    conditionals: Vec<Conditional>,
+}
+
+impl Update {
+
+    /// Creates a new UPDATE statement without values.
+    pub fn new() -> Update {
+        Update {
+            values: Vec::new(),
+            increments: Vec::new(),
+            conditionals: Vec::new(),
+        }
+    }
+
+    /// Sets the new value of a table-column.
+    pub fn set(mut self, column: TableColumn, value: Value) -> Update {
+        self.values.push((column, value));
+        self
+    }
+
+    /// Increments the value of an integer table-column by one, in the statement itself, so
+    /// concurrent increments are not lost.
+    pub fn increment(mut self, column: TableColumn) -> Update {
+        self.increments.push(column);
+        self
+    }
+
+    /// Adds a conditional the updated rows should satisfy. Conditionals are joined with AND.
+    pub fn filter(mut self, conditional: Conditional) -> Update {
+        self.conditionals.push(conditional);
+        self
+    }
+
+    /// Creates the SQL statement with its parameters.
+    pub fn build(&self) -> Query {
+        let table = self.values.first().map(|(column, _)| column)
+            .or_else(|| self.increments.first())
+            .map(TableColumn::as_table)
+            .unwrap_or("");
+        let mut assignments: Vec<String> = self.values.iter()
+            .map(|(column, _)| format!("{} = ?", column.as_column()))
+            .collect();
+        assignments.extend(self.increments.iter()
+            .map(|column| format!("{0} = {0} + 1", column.as_column())));
+        let mut sql = format!("UPDATE {} SET {}", table, assignments.join(", "));
+        let mut parameters: Vec<Value> = self.values.iter().map(|(_, value)| value.clone()).collect();
+        write_where(&mut sql, &mut parameters, &self.conditionals);
+        Query::new(sql, parameters)
+    }
+}
+
+/// Builder of SQL DELETE statements.
+#[derive(Clone, Debug)]
+pub struct Delete {
+    table: Table,
+    conditionals: Vec<Conditional>,
+}
+
+impl Delete {
+
+    /// Creates a new DELETE statement of the rows of a table.
+    pub fn from(table: Table) -> Delete {
+        Delete {
+            table: table,
+            conditionals: Vec::new(),
+        }
+    }
+
+    /// Adds a conditional the deleted rows should satisfy. Conditionals are joined with AND.
+    pub fn filter(mut self, conditional: Conditional) -> Delete {
+        self.conditionals.push(conditional);
+        self
+    }
+
+    /// Creates the SQL statement with its parameters.
+    pub fn build(&self) -> Query {
+        let mut sql = format!("DELETE FROM {}", self.table.name());
+        let mut parameters = Vec::new();
+        write_where(&mut sql, &mut parameters, &self.conditionals);
+        Query::new(sql, parameters)
+    }
+}
+
+/// Appends the WHERE clause of a statement, if there are conditionals.
+fn write_where(sql: &mut String, parameters: &mut Vec<Value>, conditionals: &[Conditional]) {
+    if conditionals.is_empty() {
+        return;
+    }
+    *sql += " WHERE ";
+    for (i, conditional) in conditionals.iter().enumerate() {
+        if i > 0 {
+            *sql += " AND ";
+        }
+        conditional.write(sql, parameters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::TableColumn::{Rolas, Performers, Albums};
+    use super::super::super::schema::{rolas, performers, albums};
+
+    #[test]
+    fn select_with_joins_order_and_pagination() {
+        let query = Select::new(&[Rolas(rolas::Title), Performers(performers::Name)])
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .filter(Conditional::Like(Rolas(rolas::Title), String::from("love")))
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Title))))
+            .limit(10)
+            .offset(20)
+            .build();
+        assert_eq!(query.sql(), "SELECT rolas.title, performers.name FROM rolas \
+            JOIN performers ON rolas.id_performer = performers.id_performer \
+            WHERE rolas.title LIKE ? ESCAPE '\\' ORDER BY rolas.title ASC LIMIT ? OFFSET ?");
+        assert_eq!(query.parameters(), &[Value::String(String::from("%love%")),
+            Value::Integer(10), Value::Integer(20)]);
+    }
+
+    #[test]
+    fn select_distinct_values_by_prefix() {
+        let query = Select::new(&[Performers(performers::Name)])
+            .distinct()
+            .filter(Conditional::StartsWith(Performers(performers::Name), String::from("1_0%")))
+            .build();
+        assert_eq!(query.sql(), "SELECT DISTINCT performers.name FROM performers \
+            WHERE performers.name LIKE ? ESCAPE '\\'");
+        assert_eq!(query.parameters(), &[Value::String(String::from("1\\_0\\%%"))]);
+    }
+
+    #[test]
+    fn select_with_groups_of_conditionals() {
+        let query = Select::new(&[Rolas(rolas::IdRola)])
+            .filter(Conditional::Or(vec![
+                Conditional::EqVal(Rolas(rolas::Genre), Value::String(String::from("Rock"))),
+                Conditional::Not(Box::new(Conditional::In(Rolas(rolas::Year),
+                    vec![Value::Integer(1990), Value::Integer(1991)]))),
+            ]))
+            .build();
+        assert_eq!(query.sql(), "SELECT rolas.id_rola FROM rolas \
+            WHERE (rolas.genre = ? OR NOT (rolas.year IN (?, ?)))");
+        assert_eq!(query.parameters().len(), 3);
+    }
+
+    #[test]
+    fn select_with_aggregates() {
+        let query = Select::fields(vec![Field::Column(Albums(albums::Name)), Field::Count(Rolas(rolas::IdRola))])
+            .join(Join::Inner(Albums(albums::IdAlbum), Rolas(rolas::IdAlbum)))
+            .group_by(Albums(albums::IdAlbum))
+            .order_by(Order::Desc(Field::Count(Rolas(rolas::IdRola))))
+            .build();
+        assert_eq!(query.sql(), "SELECT albums.name, COUNT(rolas.id_rola) FROM albums \
+            JOIN rolas ON albums.id_album = rolas.id_album GROUP BY albums.id_album \
+            ORDER BY COUNT(rolas.id_rola) DESC");
+    }
+
+    #[test]
+    fn insert_update_and_delete() {
+        let insert = Insert::new()
+            .value(Albums(albums::Name), Value::String(String::from("It's")))
+            .value(Albums(albums::Year), Value::Integer(1999))
+            .build();
+        assert_eq!(insert.sql(), "INSERT INTO albums (name, year) VALUES (?, ?)");
+        assert_eq!(insert.parameters().len(), 2);
+
+        let update = Update::new()
+            .set(Albums(albums::Year), Value::Integer(2000))
+            .filter(Conditional::EqVal(Albums(albums::IdAlbum), Value::Integer(1)))
+            .build();
+        assert_eq!(update.sql(), "UPDATE albums SET year = ? WHERE albums.id_album = ?");
+
+        let update = Update::new()
+            .increment(Rolas(rolas::PlayCount))
+            .filter(Conditional::EqVal(Rolas(rolas::IdRola), Value::Integer(1)))
+            .build();
+        assert_eq!(update.sql(),
+            "UPDATE rolas SET play_count = play_count + 1 WHERE rolas.id_rola = ?");
+
+        let delete = Delete::from(Table::Albums)
+            .filter(Conditional::IsNull(Albums(albums::Path)))
+            .build();
+        assert_eq!(delete.sql(), "DELETE FROM albums WHERE albums.path IS NULL");
+        assert!(delete.parameters().is_empty());
+    }
+}
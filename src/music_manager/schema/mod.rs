@@ -0,0 +1,644 @@
+//! Typed description of the music player database, shared by the query manager and the music
+//! database, so table and column names used in statements can only be valid ones.
+
+use super::error::{Error, Result};
+
+/// Statements from the tables.sql file, that create the database.
+pub const TABLES: &str = include_str!("../../../tables.sql");
+
+/// Statements that update a database created by an older version of the tables.sql file.
+/// The statement at index n takes a database from version n to version n + 1, so a database
+/// created from the current tables.sql file has version `MIGRATIONS.len()`.
+pub const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE rolas ADD COLUMN duration INTEGER;",
+    "ALTER TABLE albums ADD COLUMN id_performer INTEGER REFERENCES performers(id_performer); \
+    ALTER TABLE rolas ADD COLUMN disc INTEGER;",
+    "ALTER TABLE rolas ADD COLUMN rating INTEGER NOT NULL DEFAULT 0; \
+    ALTER TABLE rolas ADD COLUMN play_count INTEGER NOT NULL DEFAULT 0; \
+    ALTER TABLE rolas ADD COLUMN skip_count INTEGER NOT NULL DEFAULT 0; \
+    ALTER TABLE rolas ADD COLUMN last_played INTEGER; \
+    ALTER TABLE rolas ADD COLUMN date_added INTEGER;",
+    "CREATE TABLE history (id_history INTEGER PRIMARY KEY, id_rola INTEGER, started INTEGER, \
+    listened INTEGER, completed INTEGER, FOREIGN KEY (id_rola) REFERENCES rolas(id_rola));",
+    "CREATE TABLE playlists (id_playlist INTEGER PRIMARY KEY, name TEXT UNIQUE, rules TEXT, \
+    sort_order TEXT, max_songs INTEGER);",
+    "CREATE TABLE searches (id_search INTEGER PRIMARY KEY, name TEXT UNIQUE, query TEXT); \
+    CREATE TABLE recent_searches (id_recent INTEGER PRIMARY KEY, query TEXT UNIQUE, \
+    searched INTEGER);",
+    "ALTER TABLE rolas ADD COLUMN audio_hash TEXT; \
+    ALTER TABLE rolas ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0;",
+    "ALTER TABLE rolas ADD COLUMN missing_since INTEGER;",
+    "DELETE FROM history WHERE id_rola NOT IN (SELECT id_rola FROM rolas);",
+    "ALTER TABLE rolas ADD COLUMN modified INTEGER;",
+    "ALTER TABLE rolas ADD COLUMN bitrate INTEGER;",
+    "DELETE FROM albums WHERE id_album NOT IN \
+    (SELECT id_album FROM rolas WHERE id_album IS NOT NULL);",
+];
+
+/// Tables in the music player database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Table {
+    Types,
+    Performers,
+    Persons,
+    Groups,
+    Albums,
+    Rolas,
+    InGroup,
+    History,
+    Playlists,
+    Searches,
+    RecentSearches,
+}
+
+/// All the tables in the database, in the order of the tables.sql file.
+pub const TABLE_LIST: [Table; 11] = [
+    Table::Types,
+    Table::Performers,
+    Table::Persons,
+    Table::Groups,
+    Table::Albums,
+    Table::Rolas,
+    Table::InGroup,
+    Table::History,
+    Table::Playlists,
+    Table::Searches,
+    Table::RecentSearches,
+];
+
+impl Table {
+
+    /// Returns the name of the table.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Table::Types => "types",
+            Table::Performers => "performers",
+            Table::Persons => "persons",
+            Table::Groups => "groups",
+            Table::Albums => "albums",
+            Table::Rolas => "rolas",
+            Table::InGroup => "in_group",
+            Table::History => "history",
+            Table::Playlists => "playlists",
+            Table::Searches => "searches",
+            Table::RecentSearches => "recent_searches",
+        }
+    }
+
+    /// Given the name of a table, returns the corresponding table.
+    pub fn from_name(name: &str) -> Result<Table> {
+        match TABLE_LIST.iter().find(|table| table.name() == name) {
+            Some(table) => Ok(*table),
+            None => Err(Error::Parse(format!("Unknown table {:?}", name))),
+        }
+    }
+
+    /// Returns all the table-columns of the table, in the order of the tables.sql file.
+    pub fn columns(&self) -> Vec<TableColumn> {
+        match self {
+            Table::Types => types::COLUMNS.iter().cloned().map(TableColumn::Types).collect(),
+            Table::Performers => performers::COLUMNS.iter().cloned()
+                .map(TableColumn::Performers).collect(),
+            Table::Persons => persons::COLUMNS.iter().cloned().map(TableColumn::Persons).collect(),
+            Table::Groups => groups::COLUMNS.iter().cloned().map(TableColumn::Groups).collect(),
+            Table::Albums => albums::COLUMNS.iter().cloned().map(TableColumn::Albums).collect(),
+            Table::Rolas => rolas::COLUMNS.iter().cloned().map(TableColumn::Rolas).collect(),
+            Table::InGroup => in_group::COLUMNS.iter().cloned().map(TableColumn::InGroup).collect(),
+            Table::History => history::COLUMNS.iter().cloned().map(TableColumn::History).collect(),
+            Table::Playlists => playlists::COLUMNS.iter().cloned()
+                .map(TableColumn::Playlists).collect(),
+            Table::Searches => searches::COLUMNS.iter().cloned()
+                .map(TableColumn::Searches).collect(),
+            Table::RecentSearches => recent_searches::COLUMNS.iter().cloned()
+                .map(TableColumn::RecentSearches).collect(),
+        }
+    }
+
+    /// Returns the table-column with the row id of the table. The in_group table has a composite
+    /// key, so it has none.
+    pub fn id_column(&self) -> Option<TableColumn> {
+        match self {
+            Table::Types => Some(TableColumn::Types(types::IdType)),
+            Table::Performers => Some(TableColumn::Performers(performers::IdPerformer)),
+            Table::Persons => Some(TableColumn::Persons(persons::IdPerson)),
+            Table::Groups => Some(TableColumn::Groups(groups::IdGroup)),
+            Table::Albums => Some(TableColumn::Albums(albums::IdAlbum)),
+            Table::Rolas => Some(TableColumn::Rolas(rolas::IdRola)),
+            Table::InGroup => None,
+            Table::History => Some(TableColumn::History(history::IdHistory)),
+            Table::Playlists => Some(TableColumn::Playlists(playlists::IdPlaylist)),
+            Table::Searches => Some(TableColumn::Searches(searches::IdSearch)),
+            Table::RecentSearches => Some(TableColumn::RecentSearches(recent_searches::IdRecent)),
+        }
+    }
+}
+
+/// Table-columns in the music player database, that can be used in the SQL statements generated by
+/// the query manager.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TableColumn {
+    Types(types::Column),
+    Performers(performers::Column),
+    Persons(persons::Column),
+    Groups(groups::Column),
+    Albums(albums::Column),
+    Rolas(rolas::Column),
+    InGroup(in_group::Column),
+    History(history::Column),
+    Playlists(playlists::Column),
+    Searches(searches::Column),
+    RecentSearches(recent_searches::Column),
+}
+
+impl TableColumn {
+
+    /// Returns the table of the table-column.
+    pub fn table(&self) -> Table {
+        match self {
+            TableColumn::Types(_) => Table::Types,
+            TableColumn::Performers(_) => Table::Performers,
+            TableColumn::Persons(_) => Table::Persons,
+            TableColumn::Groups(_) => Table::Groups,
+            TableColumn::Albums(_) => Table::Albums,
+            TableColumn::Rolas(_) => Table::Rolas,
+            TableColumn::InGroup(_) => Table::InGroup,
+            TableColumn::History(_) => Table::History,
+            TableColumn::Playlists(_) => Table::Playlists,
+            TableColumn::Searches(_) => Table::Searches,
+            TableColumn::RecentSearches(_) => Table::RecentSearches,
+        }
+    }
+
+    /// Returns the table from the enum as string.
+    pub fn as_table(&self) -> &'static str {
+        self.table().name()
+    }
+
+    /// Returns the column from the enum as string, without its table.
+    pub fn as_column(&self) -> &'static str {
+        match self {
+            TableColumn::Types(column) => column.name(),
+            TableColumn::Performers(column) => column.name(),
+            TableColumn::Persons(column) => column.name(),
+            TableColumn::Groups(column) => column.name(),
+            TableColumn::Albums(column) => column.name(),
+            TableColumn::Rolas(column) => column.name(),
+            TableColumn::InGroup(column) => column.name(),
+            TableColumn::History(column) => column.name(),
+            TableColumn::Playlists(column) => column.name(),
+            TableColumn::Searches(column) => column.name(),
+            TableColumn::RecentSearches(column) => column.name(),
+        }
+    }
+
+    /// Given the names of a table and one of its columns, creates the corresponding table-column.
+    pub fn from_str(table: &str, column: &str) -> Result<TableColumn> {
+        let table = Table::from_name(table)?;
+        match table.columns().into_iter().find(|table_column| table_column.as_column() == column) {
+            Some(table_column) => Ok(table_column),
+            None => Err(Error::Parse(format!("Unknown column {:?} in table {:?}", column,
+                table.name()))),
+        }
+    }
+}
+
+impl ToString for TableColumn {
+
+    /// Returns a string representation of the table-column to be used by the query manager.
+    fn to_string(&self) -> String {
+        format!("{}.{}", self.as_table(), self.as_column())
+    }
+}
+
+/// Columns of the types table.
+pub mod types {
+
+    pub use self::Column::*;
+
+    /// Columns of the types table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdType,
+        Description,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 2] = [IdType, Description];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdType => "id_type",
+                Column::Description => "description",
+            }
+        }
+    }
+}
+
+/// Columns of the performers table.
+pub mod performers {
+
+    pub use self::Column::*;
+
+    /// Columns of the performers table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdPerformer,
+        IdType,
+        Name,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 3] = [IdPerformer, IdType, Name];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdPerformer => "id_performer",
+                Column::IdType => "id_type",
+                Column::Name => "name",
+            }
+        }
+    }
+}
+
+/// Columns of the persons table.
+pub mod persons {
+
+    pub use self::Column::*;
+
+    /// Columns of the persons table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdPerson,
+        StageName,
+        RealName,
+        BirthDate,
+        DeathDate,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 5] = [IdPerson, StageName, RealName, BirthDate, DeathDate];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdPerson => "id_person",
+                Column::StageName => "stage_name",
+                Column::RealName => "real_name",
+                Column::BirthDate => "birth_date",
+                Column::DeathDate => "death_date",
+            }
+        }
+    }
+}
+
+/// Columns of the groups table.
+pub mod groups {
+
+    pub use self::Column::*;
+
+    /// Columns of the groups table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdGroup,
+        Name,
+        StartDate,
+        EndDate,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 4] = [IdGroup, Name, StartDate, EndDate];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdGroup => "id_group",
+                Column::Name => "name",
+                Column::StartDate => "start_date",
+                Column::EndDate => "end_date",
+            }
+        }
+    }
+}
+
+/// Columns of the albums table.
+pub mod albums {
+
+    pub use self::Column::*;
+
+    /// Columns of the albums table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdAlbum,
+        Path,
+        Name,
+        Year,
+        IdPerformer,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 5] = [IdAlbum, Path, Name, Year, IdPerformer];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdAlbum => "id_album",
+                Column::Path => "path",
+                Column::Name => "name",
+                Column::Year => "year",
+                Column::IdPerformer => "id_performer",
+            }
+        }
+    }
+}
+
+/// Columns of the rolas table.
+pub mod rolas {
+
+    pub use self::Column::*;
+
+    /// Columns of the rolas table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdRola,
+        IdPerformer,
+        IdAlbum,
+        Path,
+        Title,
+        Track,
+        Year,
+        Genre,
+        Duration,
+        Disc,
+        Rating,
+        PlayCount,
+        SkipCount,
+        LastPlayed,
+        DateAdded,
+        AudioHash,
+        Hidden,
+        MissingSince,
+        Modified,
+        Bitrate,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 20] = [IdRola, IdPerformer, IdAlbum, Path, Title, Track, Year, Genre,
+        Duration, Disc, Rating, PlayCount, SkipCount, LastPlayed, DateAdded, AudioHash, Hidden,
+        MissingSince, Modified, Bitrate];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdRola => "id_rola",
+                Column::IdPerformer => "id_performer",
+                Column::IdAlbum => "id_album",
+                Column::Path => "path",
+                Column::Title => "title",
+                Column::Track => "track",
+                Column::Year => "year",
+                Column::Genre => "genre",
+                Column::Duration => "duration",
+                Column::Disc => "disc",
+                Column::Rating => "rating",
+                Column::PlayCount => "play_count",
+                Column::SkipCount => "skip_count",
+                Column::LastPlayed => "last_played",
+                Column::DateAdded => "date_added",
+                Column::AudioHash => "audio_hash",
+                Column::Hidden => "hidden",
+                Column::MissingSince => "missing_since",
+                Column::Modified => "modified",
+                Column::Bitrate => "bitrate",
+            }
+        }
+    }
+}
+
+/// Columns of the in_group table.
+pub mod in_group {
+
+    pub use self::Column::*;
+
+    /// Columns of the in_group table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdPerson,
+        IdGroup,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 2] = [IdPerson, IdGroup];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdPerson => "id_person",
+                Column::IdGroup => "id_group",
+            }
+        }
+    }
+}
+
+/// Columns of the history table.
+pub mod history {
+
+    pub use self::Column::*;
+
+    /// Columns of the history table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdHistory,
+        IdRola,
+        Started,
+        Listened,
+        Completed,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 5] = [IdHistory, IdRola, Started, Listened, Completed];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdHistory => "id_history",
+                Column::IdRola => "id_rola",
+                Column::Started => "started",
+                Column::Listened => "listened",
+                Column::Completed => "completed",
+            }
+        }
+    }
+}
+
+/// Columns of the playlists table.
+pub mod playlists {
+
+    pub use self::Column::*;
+
+    /// Columns of the playlists table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdPlaylist,
+        Name,
+        Rules,
+        SortOrder,
+        MaxSongs,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 5] = [IdPlaylist, Name, Rules, SortOrder, MaxSongs];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdPlaylist => "id_playlist",
+                Column::Name => "name",
+                Column::Rules => "rules",
+                Column::SortOrder => "sort_order",
+                Column::MaxSongs => "max_songs",
+            }
+        }
+    }
+}
+
+/// Columns of the searches table.
+pub mod searches {
+
+    pub use self::Column::*;
+
+    /// Columns of the searches table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdSearch,
+        Name,
+        Query,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 3] = [IdSearch, Name, Query];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdSearch => "id_search",
+                Column::Name => "name",
+                Column::Query => "query",
+            }
+        }
+    }
+}
+
+/// Columns of the recent_searches table.
+pub mod recent_searches {
+
+    pub use self::Column::*;
+
+    /// Columns of the recent_searches table, in the order of the tables.sql file.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum Column {
+        IdRecent,
+        Query,
+        Searched,
+    }
+
+    /// All the columns of the table.
+    pub const COLUMNS: [Column; 3] = [IdRecent, Query, Searched];
+
+    impl Column {
+
+        /// Returns the name of the column.
+        pub fn name(&self) -> &'static str {
+            match self {
+                Column::IdRecent => "id_recent",
+                Column::Query => "query",
+                Column::Searched => "searched",
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the columns of every table created by the tables.sql file, in order.
+    fn tables_sql() -> Vec<(String, Vec<String>)> {
+        let mut tables = Vec::new();
+        for statement in TABLES.split(';') {
+            let statement = statement.trim();
+            if !statement.starts_with("CREATE TABLE") {
+                continue;
+            }
+            let name = statement["CREATE TABLE".len()..].split('(').next().unwrap().trim();
+            let mut columns = Vec::new();
+            for line in statement.lines().skip(1) {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with(')') || line.starts_with("PRIMARY KEY")
+                    || line.starts_with("FOREIGN KEY") {
+                    continue;
+                }
+                columns.push(line.split_whitespace().next().unwrap().to_owned());
+            }
+            tables.push((name.to_owned(), columns));
+        }
+        tables
+    }
+
+    #[test]
+    fn schema_matches_tables_sql() {
+        let tables = tables_sql();
+        let names: Vec<&str> = tables.iter().map(|(name, _)| &name[..]).collect();
+        let schema_names: Vec<&str> = TABLE_LIST.iter().map(Table::name).collect();
+        assert_eq!(names, schema_names);
+        for (name, columns) in &tables {
+            let table = Table::from_name(name).unwrap();
+            let schema_columns: Vec<&str> = table.columns().iter().map(TableColumn::as_column)
+                .collect();
+            assert_eq!(columns, &schema_columns, "columns of {}", name);
+        }
+    }
+
+    #[test]
+    fn table_columns_belong_to_their_table() {
+        for table in TABLE_LIST.iter() {
+            for column in table.columns() {
+                assert_eq!(column.table(), *table);
+                assert_eq!(TableColumn::from_str(table.name(), column.as_column()).unwrap(), column);
+            }
+            if let Some(id_column) = table.id_column() {
+                assert_eq!(id_column.table(), *table);
+            }
+        }
+    }
+
+    #[test]
+    fn only_table_names_are_parsed() {
+        assert_eq!(Table::from_name("albums").unwrap(), Table::Albums);
+        assert!(Table::from_name("album").is_err());
+        assert!(Table::from_name("albumss").is_err());
+        assert!(TableColumn::from_str("albums", "title").is_err());
+        assert_eq!(TableColumn::Rolas(rolas::Title).to_string(), "rolas.title");
+    }
+}
@@ -1,65 +1,393 @@
-use super::{music_database::MusicDatabase};
+use std::{collections::HashSet, iter};
+use super::{music_database::{self, MusicDatabase}, error::{Error, Result}, config::Config};
+use super::model::{Song, SmartPlaylist, PlaylistOrder, SavedSearch};
+use super::query_manager::{
+    Query, Select, Join, Conditional, Field, Order, Value,
+    TableColumn,
+    TableColumn::Rolas as Rolas,
+    TableColumn::Performers as Performers,
+    TableColumn::Albums as Albums,
+};
+use super::schema::{rolas, performers, albums};
 
-/// A search manager has a music database connection and a vector of results (that contain the songs'
-/// title) after a search is made.
+/// A search manager has a music database connection and the ids of the songs that match the
+/// current search, if there is one. It is meant to live as long as the search bar, so every
+/// search uses the same connection.
 pub struct SearchManager {
-    results: Vec<String>,
+    results: Option<HashSet<i64>>,
     database: MusicDatabase,
 }
 
 impl SearchManager {
 
-    /// Creates a new instance of a search manager with an active database connection.
-    pub fn new() -> SearchManager {
-        let mut database = MusicDatabase::new();
-        database.connect().unwrap();
+    /// Creates a new instance of a search manager with an active connection to the database of
+    /// a configuration.
+    pub fn new(config: &Config) -> Result<SearchManager> {
+        Ok(SearchManager::with_database(config.database()?))
+    }
 
+    /// Creates a new instance of a search manager that uses the given database connection.
+    pub fn with_database(database: MusicDatabase) -> SearchManager {
         SearchManager {
-            results: Vec::new(),
+            results: None,
             database: database,
         }
     }
 
     /// Given a query from the user, the search manager creates the corresponding SQL statement.
     /// If the statement is valid, it makes the query to the database, storing the results.
-    pub fn set_rules(&mut self, query: &str) {
-        let mut value = String::new();
-        let mut search = String::from("SELECT rolas.title FROM rolas");
-        let mut valid_search = false;
-        for word in query.split_whitespace() {
-            match word {
-                "T:" => {
-                    search += " WHERE rolas.title ";
-                    valid_search = true;
-                },
-                "P:" => {
-                    search += ", performers WHERE rolas.id_performer = performers.id_performer \
-                    AND performers.name ";
-                    valid_search = true;
-                },
-                "A:" => {
-                    search += ", albums WHERE rolas.id_album = albums.id_album \
-                    AND albums.name ";
-                    valid_search = true;
-                },
-                "G:" => {
-                    search += " WHERE rolas.genre ";
-                    valid_search = true;
-                },
-                _ => {
-                    value = format!("{} {}", value, word);
+    /// Several prefixes can be combined, e.g. "P: artist G: rock", and all of them must match.
+    /// A query without prefixes clears the search, so every song is visible. An invalid query
+    /// keeps the previous results.
+    pub fn set_rules(&mut self, query: &str) -> Result<()> {
+        let conditionals = parse_rules(query)?;
+        if conditionals.is_empty() {
+            self.results = None;
+            return Ok(());
+        }
+        let mut search = Select::new(&[Rolas(rolas::IdRola)])
+            .join(Join::Inner(Rolas(rolas::IdPerformer), Performers(performers::IdPerformer)))
+            .join(Join::Inner(Rolas(rolas::IdAlbum), Albums(albums::IdAlbum)));
+        for conditional in conditionals {
+            search = search.filter(conditional);
+        }
+        let ids = self.database.search_songs(&search.build())?;
+        self.results = Some(ids.into_iter().collect());
+        Ok(())
+    }
+
+    /// Given a query from the user, returns the songs that match it, ordered by title.
+    /// A query without prefixes matches every song.
+    pub fn songs(&self, query: &str) -> Result<Vec<Song>> {
+        let mut search = music_database::songs_select()
+            .order_by(Order::Asc(Field::Column(Rolas(rolas::Title))));
+        for conditional in parse_rules(query)? {
+            search = search.filter(conditional);
+        }
+        self.database.songs_from(&search.build())
+    }
+
+    /// Adds a query that was searched to the recent searches, if it is a valid query with
+    /// prefixes.
+    pub fn remember(&self, query: &str) -> Result<()> {
+        let query = query.trim();
+        if parse_rules(query)?.is_empty() {
+            return Ok(());
+        }
+        self.database.add_recent_search(query)
+    }
+
+    /// Returns the queries of the recent searches, the latest first.
+    pub fn recent_searches(&self) -> Result<Vec<String>> {
+        self.database.recent_searches()
+    }
+
+    /// Returns the saved searches, ordered by name.
+    pub fn saved_searches(&self) -> Result<Vec<SavedSearch>> {
+        self.database.saved_searches()
+    }
+
+    /// Saves a query with a name if it is valid, replacing the saved search with the same name.
+    /// Returns the id of the saved search.
+    pub fn save_search(&self, name: &str, query: &str) -> Result<i64> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::Parse(String::from("The search has no name")));
+        }
+        parse_rules(query)?;
+        self.database.save_search(name, query.trim())
+    }
+
+    /// Removes a saved search.
+    pub fn delete_search(&self, id_search: i64) -> Result<()> {
+        self.database.delete_search(id_search)
+    }
+
+    /// Returns the songs of a smart playlist: the songs that currently match its rules, in its
+    /// order.
+    pub fn playlist_songs(&self, playlist: &SmartPlaylist) -> Result<Vec<Song>> {
+        self.database.songs_from(&playlist_query(playlist)?)
+    }
+
+    /// Stores a smart playlist if its rules are a valid query, replacing the one with the same
+    /// name. Returns the id of the playlist.
+    pub fn save_playlist(&self, playlist: &SmartPlaylist) -> Result<i64> {
+        parse_rules(&playlist.rules)?;
+        self.database.save_playlist(playlist)
+    }
+
+    /// Returns completions of the value being written after the last prefix of a query, e.g.
+    /// "G: rock P: the b" is completed with "G: rock P: The Band" and "G: rock P: The Beatles",
+    /// from the values stored in the prefix's column. Numeric prefixes are not completed.
+    pub fn completions(&self, query: &str) -> Result<Vec<String>> {
+        let (column, value_start) = match last_text_prefix(query) {
+            Some(last_prefix) => last_prefix,
+            None => return Ok(Vec::new()),
+        };
+        let value = query[value_start..].trim();
+        let mut head = query[..value_start].to_owned();
+        if value.is_empty() && !head.ends_with(char::is_whitespace) {
+            head.push(' ');
+        }
+        let values = self.database.values_starting_with(column, value, COMPLETION_LIMIT)?;
+        Ok(values.into_iter()
+            .filter(|completion| !completion.eq_ignore_ascii_case(value))
+            .map(|completion| format!("{}{}", head, completion))
+            .collect())
+    }
+
+    /// Given a song's id, it determines whether the song should be visible or not.
+    pub fn is_visible(&self, id: i64) -> bool {
+        self.results.as_ref().map_or(true, |results| results.contains(&id))
+    }
+
+}
+
+/// Returns the statement that selects the songs of a smart playlist, with the columns read by
+/// `MusicDatabase::songs_from`.
+pub fn playlist_query(playlist: &SmartPlaylist) -> Result<Query> {
+    let mut query = music_database::songs_select();
+    for conditional in parse_rules(&playlist.rules)? {
+        query = query.filter(conditional);
+    }
+    for order in playlist_orders(playlist.order) {
+        query = query.order_by(order);
+    }
+    if let Some(limit) = playlist.limit {
+        query = query.limit(limit);
+    }
+    Ok(query.build())
+}
+
+/// Returns the orders of the songs of a smart playlist. Ties are ordered by title.
+fn playlist_orders(order: PlaylistOrder) -> Vec<Order> {
+    let column = |column| Field::Column(Rolas(column));
+    let by_title = Order::Asc(column(rolas::Title));
+    let order = match order {
+        PlaylistOrder::Title => return vec![by_title],
+        PlaylistOrder::Random => return vec![Order::Random],
+        PlaylistOrder::MostPlayed => Order::Desc(column(rolas::PlayCount)),
+        PlaylistOrder::LeastPlayed => Order::Asc(column(rolas::PlayCount)),
+        PlaylistOrder::TopRated => Order::Desc(column(rolas::Rating)),
+        PlaylistOrder::RecentlyAdded => Order::Desc(column(rolas::DateAdded)),
+        PlaylistOrder::RecentlyPlayed => Order::Desc(column(rolas::LastPlayed)),
+    };
+    vec![order, by_title]
+}
+
+/// Largest number of values completed after a prefix.
+const COMPLETION_LIMIT: i64 = 20;
+
+/// Prefixes of the search language, with the table-column they search and whether its values
+/// are compared as numbers.
+const PREFIXES: [(&str, TableColumn, bool); 7] = [
+    ("T:", Rolas(rolas::Title), false),
+    ("P:", Performers(performers::Name), false),
+    ("A:", Albums(albums::Name), false),
+    ("G:", Rolas(rolas::Genre), false),
+    ("Y:", Rolas(rolas::Year), true),
+    ("R:", Rolas(rolas::Rating), true),
+    ("PC:", Rolas(rolas::PlayCount), true),
+];
+
+/// Splits a word of a query that starts with a prefix into the prefix's table-column, whether
+/// it is numeric, and the rest of the word, e.g. "R:>=4" into the rating column and ">=4".
+fn split_prefix(word: &str) -> Option<(TableColumn, bool, &str)> {
+    PREFIXES.iter()
+        .find(|&&(prefix, _, _)| word.starts_with(prefix))
+        .map(|&(prefix, column, numeric)| (column, numeric, &word[prefix.len()..]))
+}
+
+/// Finds the last prefix of a query if it searches a text column. Returns the column and the
+/// byte index of the query where the value searched in it starts.
+fn last_text_prefix(query: &str) -> Option<(TableColumn, usize)> {
+    let mut last = None;
+    let mut word_start = None;
+    for (index, c) in query.char_indices().chain(iter::once((query.len(), ' '))) {
+        match (word_start, c.is_whitespace()) {
+            (None, false) => word_start = Some(index),
+            (Some(start), true) => {
+                if let Some((column, numeric, rest)) = split_prefix(&query[start..index]) {
+                    last = Some((column, numeric, index - rest.len()));
+                }
+                word_start = None;
+            },
+            _ => {},
+        }
+    }
+    let (column, numeric, value_start) = last?;
+    if numeric {
+        return None;
+    }
+    let value = &query[value_start..];
+    Some((column, value_start + value.len() - value.trim_start().len()))
+}
+
+/// Parses a query of the search language into conditionals. Words after a prefix are the value
+/// searched in the prefix's column: text columns match values that contain it, numeric columns
+/// match a number, optionally preceded by a comparison (">=4", "<2", "≥4"). The words "never
+/// played" outside a prefix match the songs with no plays. An "AND" before a prefix only
+/// separates the rules, as all of them must match. Other words before any prefix are ignored.
+fn parse_rules(query: &str) -> Result<Vec<Conditional>> {
+    let mut conditionals = Vec::new();
+    let mut column: Option<(TableColumn, bool)> = None;
+    let mut value = String::new();
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut index = 0;
+    while index < words.len() {
+        if column.is_none() && is_never_played(&words[index..]) {
+            conditionals.push(Conditional::EqVal(Rolas(rolas::PlayCount), Value::Integer(0)));
+            index += 2;
+            continue;
+        }
+        let word = words[index];
+        index += 1;
+        if word == "AND" && words.get(index).map_or(false, |next| split_prefix(next).is_some()) {
+            continue;
+        }
+        match split_prefix(word) {
+            Some((next_column, numeric, rest)) => {
+                if let Some(column) = column.take() {
+                    conditionals.push(conditional(column, value.trim())?);
                 }
+                column = Some((next_column, numeric));
+                value = rest.to_owned();
+            },
+            None => {
+                value = format!("{} {}", value, word);
             }
-        };
-        if valid_search {
-            search += &format!("LIKE '%{}%'", value.trim());
-            self.results = self.database.search_songs(&search);
         }
     }
+    if let Some(column) = column {
+        conditionals.push(conditional(column, value.trim())?);
+    }
+    Ok(conditionals)
+}
+
+/// Checks if the words start with "never played", in any case.
+fn is_never_played(words: &[&str]) -> bool {
+    words.len() >= 2 && words[0].eq_ignore_ascii_case("never")
+        && words[1].eq_ignore_ascii_case("played")
+}
 
-    /// Given a song's title, it determines whether the song should be visible or not.
-    pub fn is_visible(&self, title: &str) -> bool {
-        self.results.contains(&title.to_string())
+/// Returns the conditional of a prefix's table-column and the value searched in it.
+fn conditional((column, numeric): (TableColumn, bool), value: &str) -> Result<Conditional> {
+    if !numeric {
+        return Ok(Conditional::Like(column, value.to_owned()));
     }
+    let value: String = value.split_whitespace().collect();
+    let operator = [">=", "<=", "≥", "≤", ">", "<", "="].iter()
+        .find(|operator| value.starts_with(**operator))
+        .cloned()
+        .unwrap_or("");
+    let number = value[operator.len()..].parse::<i64>()
+        .map_err(|_| Error::Parse(format!("Invalid number {:?} in the search", value)))?;
+    let number = Value::Integer(number);
+    Ok(match operator {
+        ">=" | "≥" => Conditional::Ge(column, number),
+        "<=" | "≤" => Conditional::Le(column, number),
+        ">" => Conditional::Gt(column, number),
+        "<" => Conditional::Lt(column, number),
+        _ => Conditional::EqVal(column, number),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::miner::music_file::MusicFile;
 
+    #[test]
+    fn numeric_prefixes_are_compared() {
+        let conditionals = parse_rules("R:>=4 G: rock").unwrap();
+        assert_eq!(conditionals.len(), 2);
+        match conditionals[0] {
+            Conditional::Ge(column, Value::Integer(4)) => assert_eq!(column, Rolas(rolas::Rating)),
+            ref other => panic!("Unexpected conditional {:?}", other),
+        }
+        match conditionals[1] {
+            Conditional::Like(column, ref value) => {
+                assert_eq!((column, value.as_str()), (Rolas(rolas::Genre), "rock"));
+            },
+            ref other => panic!("Unexpected conditional {:?}", other),
+        }
+        assert!(parse_rules("R: lots").is_err());
+    }
+
+    #[test]
+    fn never_played_matches_no_plays() {
+        let conditionals = parse_rules("Never played R: 5").unwrap();
+        assert_eq!(conditionals.len(), 2);
+        match conditionals[0] {
+            Conditional::EqVal(column, Value::Integer(0)) => {
+                assert_eq!(column, Rolas(rolas::PlayCount));
+            },
+            ref other => panic!("Unexpected conditional {:?}", other),
+        }
+        assert_eq!(parse_rules("T: never played").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn playlists_follow_the_library() {
+        let mut database = MusicDatabase::new();
+        database.with_database(":memory:");
+        database.connect().unwrap();
+        for &(file, genre, year) in [("a.mp3", "Rock", 1975), ("b.mp3", "Rock", 1991),
+            ("c.mp3", "Jazz", 1960), ("d.mp3", "Rock", 1969)].iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            tag.set_genre(genre);
+            tag.set_year(year);
+            let path = ::std::path::PathBuf::from("/music").join(file);
+            database.save_song(MusicFile::from_tag(path, &tag)).unwrap();
+        }
+        let ids: Vec<i64> = database.songs().unwrap().iter().map(|song| song.id).collect();
+        database.set_rating(ids[0], 5).unwrap();
+        database.set_rating(ids[3], 4).unwrap();
+        database.record_play(ids[3]).unwrap();
+        let search_manager = SearchManager::with_database(database);
+        let mut playlist = SmartPlaylist {
+            id: 0,
+            name: String::from("Classics"),
+            rules: String::from("G: rock AND Y: < 1980 AND R: ≥ 4"),
+            order: PlaylistOrder::MostPlayed,
+            limit: Some(50),
+        };
+        let titles = |playlist: &SmartPlaylist| -> Vec<String> {
+            search_manager.playlist_songs(playlist).unwrap().into_iter()
+                .map(|song| song.title)
+                .collect()
+        };
+        assert_eq!(titles(&playlist), vec!["d.mp3", "a.mp3"]);
+        playlist.limit = Some(1);
+        playlist.order = PlaylistOrder::TopRated;
+        assert_eq!(titles(&playlist), vec!["a.mp3"]);
+
+        playlist.rules = String::from("Y: old");
+        assert!(search_manager.save_playlist(&playlist).is_err());
+    }
+
+    #[test]
+    fn values_are_completed() {
+        let mut database = MusicDatabase::new();
+        database.with_database(":memory:");
+        database.connect().unwrap();
+        for &(file, performer) in [("a.mp3", "The Beatles"), ("b.mp3", "The Band"),
+            ("c.mp3", "Queen"), ("d.mp3", "50% Off"), ("e.mp3", "50 Cent")].iter() {
+            let mut tag = ::id3::Tag::new();
+            tag.set_title(file);
+            tag.set_artist(performer);
+            let path = ::std::path::PathBuf::from("/music").join(file);
+            database.save_song(MusicFile::from_tag(path, &tag)).unwrap();
+        }
+        let search_manager = SearchManager::with_database(database);
+        assert_eq!(search_manager.completions("G: rock P: the b").unwrap(),
+            vec!["G: rock P: The Band", "G: rock P: The Beatles"]);
+        assert_eq!(search_manager.completions("P:").unwrap(),
+            vec!["P: 50 Cent", "P: 50% Off", "P: Queen", "P: The Band", "P: The Beatles"]);
+        assert_eq!(search_manager.completions("P: 50%").unwrap(), vec!["P: 50% Off"]);
+        assert!(search_manager.completions("P: Queen").unwrap().is_empty());
+        assert!(search_manager.completions("R:>").unwrap().is_empty());
+        assert!(search_manager.completions("queen").unwrap().is_empty());
+    }
 }
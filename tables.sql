@@ -33,7 +33,9 @@ CREATE TABLE albums (
         id_album        INTEGER PRIMARY KEY,
         path            TEXT,
         name            TEXT,
-        year            INTEGER
+        year            INTEGER,
+        id_performer    INTEGER,
+        FOREIGN KEY     (id_performer) REFERENCES performers(id_performer)
 );
 
 CREATE TABLE rolas (
@@ -45,6 +47,18 @@ CREATE TABLE rolas (
         track           INTEGER,
         year            INTEGER,
         genre           TEXT,
+        duration        INTEGER,
+        disc            INTEGER,
+        rating          INTEGER NOT NULL DEFAULT 0,
+        play_count      INTEGER NOT NULL DEFAULT 0,
+        skip_count      INTEGER NOT NULL DEFAULT 0,
+        last_played     INTEGER,
+        date_added      INTEGER,
+        audio_hash      TEXT,
+        hidden          INTEGER NOT NULL DEFAULT 0,
+        missing_since   INTEGER,
+        modified        INTEGER,
+        bitrate         INTEGER,
         FOREIGN KEY     (id_performer) REFERENCES performers(id_performer),
         FOREIGN KEY     (id_album) REFERENCES albums(id_album)
 );
@@ -56,3 +70,32 @@ CREATE TABLE in_group (
         FOREIGN KEY     (id_person) REFERENCES persons(id_person),
         FOREIGN KEY     (id_group) REFERENCES groups(id_group)
 );
+
+CREATE TABLE history (
+        id_history      INTEGER PRIMARY KEY,
+        id_rola         INTEGER,
+        started         INTEGER,
+        listened        INTEGER,
+        completed       INTEGER,
+        FOREIGN KEY     (id_rola) REFERENCES rolas(id_rola)
+);
+
+CREATE TABLE playlists (
+        id_playlist     INTEGER PRIMARY KEY,
+        name            TEXT UNIQUE,
+        rules           TEXT,
+        sort_order      TEXT,
+        max_songs       INTEGER
+);
+
+CREATE TABLE searches (
+        id_search       INTEGER PRIMARY KEY,
+        name            TEXT UNIQUE,
+        query           TEXT
+);
+
+CREATE TABLE recent_searches (
+        id_recent       INTEGER PRIMARY KEY,
+        query           TEXT UNIQUE,
+        searched        INTEGER
+);